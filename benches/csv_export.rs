@@ -39,12 +39,15 @@ tiger::pos::pcc2/4282#tok_73 tiger::pos::pcc2/4282#tok_74
                 corpora: vec!["pcc2".into()],
                 query_language: graphannis::corpusstorage::QueryLanguage::AQL,
                 limit: None,
+                offset: 0,
                 order: ResultOrder::Normal,
             };
             let config = CSVConfig {
                 span_segmentation: None,
                 left_context: 0,
                 right_context: 0,
+                sort_position: None,
+                sort_annotation: None,
             };
             let session_arg = SessionArg::Id(String::default());
             let mut string_buffer = Vec::new();
@@ -0,0 +1,223 @@
+//! Citations of a query and the corpora it was executed on, formatted as
+//! BibTeX entries or in APA style.
+
+use chrono::{NaiveDate, Utc};
+use graphannis_core::util::split_qname;
+use serde::Serialize;
+
+use crate::{
+    client::corpora,
+    state::{GlobalAppState, SessionArg},
+    views::corpora::node_metadata,
+    Result,
+};
+
+/// Bibliographic information about a corpus, taken from the metadata of its
+/// top-level corpus node.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct CorpusCitation {
+    pub name: String,
+    pub title: String,
+    pub version: Option<String>,
+    pub authors: Vec<String>,
+    pub year: Option<String>,
+}
+
+impl CorpusCitation {
+    /// Create the citation from the metadata of the corpus. The names of the
+    /// metadata are compared case-insensitive and without their namespace.
+    /// Multiple authors are separated by a semicolon.
+    pub fn from_metadata(name: &str, metadata: &[(String, String)]) -> Self {
+        let get = |keys: &[&str]| -> Option<String> {
+            keys.iter().find_map(|key| {
+                metadata.iter().find_map(|(qname, value)| {
+                    let (_, anno_name) = split_qname(qname);
+                    if anno_name.eq_ignore_ascii_case(key) && !value.trim().is_empty() {
+                        Some(value.trim().to_string())
+                    } else {
+                        None
+                    }
+                })
+            })
+        };
+
+        let authors = get(&["author", "authors", "creator"])
+            .map(|authors| {
+                authors
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|a| !a.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        // Only use the year of a full date
+        let year = get(&["year", "date"])
+            .map(|date| {
+                date.chars()
+                    .take_while(char::is_ascii_digit)
+                    .collect::<String>()
+            })
+            .filter(|year| !year.is_empty());
+
+        Self {
+            name: name.to_string(),
+            title: get(&["title", "full_name"]).unwrap_or_else(|| name.to_string()),
+            version: get(&["version"]),
+            authors,
+            year,
+        }
+    }
+}
+
+/// Get the citation information of a corpus from its metadata.
+pub async fn corpus_citation(
+    session: &SessionArg,
+    corpus: &str,
+    state: &GlobalAppState,
+) -> Result<CorpusCitation> {
+    let corpus_graph = corpora::corpus_graph(session, corpus, state).await?;
+    let metadata = if let Some(corpus_node) = corpus_graph.get_node_id_from_name(corpus)? {
+        node_metadata(&corpus_graph, corpus_node, &[])?
+    } else {
+        Vec::new()
+    };
+    Ok(CorpusCitation::from_metadata(corpus, &metadata))
+}
+
+/// A citation of a query, which can be reproduced with the permalink, and of
+/// the corpora it was executed on.
+#[derive(Serialize, Debug, Clone)]
+pub struct Citation {
+    pub query: String,
+    pub permalink: String,
+    pub corpora: Vec<CorpusCitation>,
+    pub accessed: NaiveDate,
+}
+
+fn escape_bibtex(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => result.push_str("\\textbackslash{}"),
+            '{' | '}' | '%' | '&' | '#' | '_' | '$' => {
+                result.push('\\');
+                result.push(c);
+            }
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+/// Join the authors in the APA style, e.g. `A, B, & C`.
+fn apa_authors(authors: &[String]) -> String {
+    match authors {
+        [] => String::new(),
+        [author] => author.clone(),
+        [first @ .., last] => format!("{}, & {last}", first.join(", ")),
+    }
+}
+
+impl Citation {
+    /// Cite the query and the given corpora, accessed today.
+    pub async fn create(
+        session: &SessionArg,
+        query: &str,
+        permalink: String,
+        corpora: &[String],
+        state: &GlobalAppState,
+    ) -> Result<Self> {
+        let mut corpus_citations = Vec::with_capacity(corpora.len());
+        for corpus in corpora {
+            corpus_citations.push(corpus_citation(session, corpus, state).await?);
+        }
+        Ok(Self {
+            query: query.to_string(),
+            permalink,
+            corpora: corpus_citations,
+            accessed: Utc::now().date_naive(),
+        })
+    }
+
+    /// One `@misc` entry for each corpus, which all reference the query.
+    pub fn bibtex(&self) -> String {
+        let mut entries = Vec::with_capacity(self.corpora.len());
+        for corpus in self.corpora.iter() {
+            let mut key: String = corpus
+                .name
+                .chars()
+                .filter(char::is_ascii_alphanumeric)
+                .collect();
+            if key.is_empty() {
+                key = "corpus".to_string();
+            }
+            let mut fields = vec![("title", escape_bibtex(&corpus.title))];
+            if !corpus.authors.is_empty() {
+                let authors: Vec<_> = corpus.authors.iter().map(|a| escape_bibtex(a)).collect();
+                fields.push(("author", authors.join(" and ")));
+            }
+            if let Some(year) = &corpus.year {
+                fields.push(("year", escape_bibtex(year)));
+            }
+            if let Some(version) = &corpus.version {
+                fields.push(("version", escape_bibtex(version)));
+            }
+            fields.push(("url", self.permalink.clone()));
+            fields.push(("urldate", self.accessed.to_string()));
+            fields.push((
+                "note",
+                format!("ANNIS query: \\texttt{{{}}}", escape_bibtex(&self.query)),
+            ));
+
+            let fields: Vec<_> = fields
+                .into_iter()
+                .map(|(name, value)| format!("  {name} = {{{value}}},"))
+                .collect();
+            entries.push(format!("@misc{{{key},\n{}\n}}", fields.join("\n")));
+        }
+        entries.join("\n\n")
+    }
+
+    /// One reference for each corpus in the APA style, followed by the query
+    /// and the permalink.
+    pub fn apa(&self) -> String {
+        let mut lines = Vec::with_capacity(self.corpora.len() + 1);
+        for corpus in self.corpora.iter() {
+            let year = corpus.year.as_deref().unwrap_or("n.d.");
+            let title = match &corpus.version {
+                Some(version) => format!("{} (Version {version}) [Data set]", corpus.title),
+                None => format!("{} [Data set]", corpus.title),
+            };
+            // Without authors, the title is moved to the position of the authors
+            if corpus.authors.is_empty() {
+                lines.push(format!("{title}. ({year})."));
+            } else {
+                lines.push(format!(
+                    "{} ({year}). {title}.",
+                    apa_authors(&corpus.authors)
+                ));
+            }
+        }
+        lines.push(format!(
+            "ANNIS query \"{}\", retrieved {} from {}",
+            self.query, self.accessed, self.permalink
+        ));
+        lines.join("\n")
+    }
+
+    /// The APA citation as a block of comment lines, which can be put in
+    /// front of an exported file.
+    pub fn header(&self) -> String {
+        let mut result = String::new();
+        for line in self.apa().lines() {
+            result.push_str("# ");
+            result.push_str(line);
+            result.push('\n');
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,109 @@
+use chrono::NaiveDate;
+use pretty_assertions::assert_eq;
+
+use super::{Citation, CorpusCitation};
+
+fn metadata(entries: &[(&str, &str)]) -> Vec<(String, String)> {
+    entries
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn corpus_citation_from_metadata() {
+    let citation = CorpusCitation::from_metadata(
+        "pcc2",
+        &metadata(&[
+            ("full_name", "Potsdam Commentary Corpus"),
+            ("version", "7.0"),
+            ("meta::Author", "Stede, M.; Neumann, A."),
+            ("date", "2014-05-26"),
+        ]),
+    );
+    assert_eq!(
+        CorpusCitation {
+            name: "pcc2".to_string(),
+            title: "Potsdam Commentary Corpus".to_string(),
+            version: Some("7.0".to_string()),
+            authors: vec!["Stede, M.".to_string(), "Neumann, A.".to_string()],
+            year: Some("2014".to_string()),
+        },
+        citation
+    );
+
+    // The corpus name is used if there is no title
+    let citation = CorpusCitation::from_metadata("RIDGES", &[]);
+    assert_eq!("RIDGES", citation.title);
+    assert_eq!(None, citation.version);
+    assert!(citation.authors.is_empty());
+}
+
+fn example_citation() -> Citation {
+    Citation {
+        query: "pos=\"NN\" & tok_{1}".to_string(),
+        permalink: "http://127.0.0.1:3000/permalink?corpora=pcc2&query=tok".to_string(),
+        corpora: vec![
+            CorpusCitation {
+                name: "pcc2".to_string(),
+                title: "Potsdam Commentary Corpus".to_string(),
+                version: Some("7.0".to_string()),
+                authors: vec![
+                    "Stede, M.".to_string(),
+                    "Neumann, A.".to_string(),
+                    "Bourgonje, P.".to_string(),
+                ],
+                year: Some("2014".to_string()),
+            },
+            CorpusCitation {
+                name: "RIDGES_Herbology_Version9.0".to_string(),
+                title: "RIDGES_Herbology_Version9.0".to_string(),
+                version: None,
+                authors: Vec::new(),
+                year: None,
+            },
+        ],
+        accessed: NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
+    }
+}
+
+#[test]
+fn bibtex_citation() {
+    assert_eq!(
+        r#"@misc{pcc2,
+  title = {Potsdam Commentary Corpus},
+  author = {Stede, M. and Neumann, A. and Bourgonje, P.},
+  year = {2014},
+  version = {7.0},
+  url = {http://127.0.0.1:3000/permalink?corpora=pcc2&query=tok},
+  urldate = {2023-10-01},
+  note = {ANNIS query: \texttt{pos="NN" \& tok\_\{1\}}},
+}
+
+@misc{RIDGESHerbologyVersion90,
+  title = {RIDGES\_Herbology\_Version9.0},
+  url = {http://127.0.0.1:3000/permalink?corpora=pcc2&query=tok},
+  urldate = {2023-10-01},
+  note = {ANNIS query: \texttt{pos="NN" \& tok\_\{1\}}},
+}"#,
+        example_citation().bibtex()
+    );
+}
+
+#[test]
+fn apa_citation_and_header() {
+    let citation = example_citation();
+    assert_eq!(
+        r#"Stede, M., Neumann, A., & Bourgonje, P. (2014). Potsdam Commentary Corpus (Version 7.0) [Data set].
+RIDGES_Herbology_Version9.0 [Data set]. (n.d.).
+ANNIS query "pos="NN" & tok_{1}", retrieved 2023-10-01 from http://127.0.0.1:3000/permalink?corpora=pcc2&query=tok"#,
+        citation.apa()
+    );
+    assert_eq!(
+        r#"# Stede, M., Neumann, A., & Bourgonje, P. (2014). Potsdam Commentary Corpus (Version 7.0) [Data set].
+# RIDGES_Herbology_Version9.0 [Data set]. (n.d.).
+# ANNIS query "pos="NN" & tok_{1}", retrieved 2023-10-01 from http://127.0.0.1:3000/permalink?corpora=pcc2&query=tok
+"#,
+        citation.header()
+    );
+}
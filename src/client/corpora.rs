@@ -1,4 +1,12 @@
-use graphannis::AnnotationGraph;
+use std::collections::BTreeMap;
+
+use axum::http::{header, HeaderValue};
+use graphannis::{
+    corpusstorage::QueryLanguage,
+    graph::Annotation,
+    model::{AnnotationComponent, AnnotationComponentType},
+    AnnotationGraph,
+};
 use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
 use serde::{Deserialize, Serialize};
 
@@ -27,6 +35,8 @@ struct SubgraphRequest {
 }
 
 const QUERY: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'#').add(b'<').add(b'>');
+/// File names are used as a single path segment and can contain slashes.
+const FILE_NAME: &AsciiSet = &QUERY.add(b'?').add(b'%').add(b'/');
 
 /// Get the subgraph for a given match
 pub async fn subgraph(
@@ -59,14 +69,91 @@ pub async fn subgraph(
     let response = client.execute(request).await?;
     if response.status().is_success() {
         let response_body = response.text().await?;
+        import_graphml(&response_body)
+    } else {
+        Err(AppError::Backend {
+            status_code: response.status(),
+            url: response.url().clone(),
+        })
+    }
+}
+
+fn import_graphml(graphml: &str) -> Result<AnnotationGraph> {
+    let (g, _config) = graphannis_core::graph::serialization::graphml::import::<
+        AnnotationComponentType,
+        _,
+        _,
+    >(graphml.as_bytes(), true, |_| {})?;
+    Ok(g)
+}
+
+#[derive(Serialize)]
+struct SubgraphForQueryRequest<'a> {
+    query: &'a str,
+    query_language: QueryLanguage,
+    component_type_filter: Option<AnnotationComponentType>,
+}
+
+/// Get the graph of all sub-corpora and documents of a corpus, including
+/// their metadata.
+pub async fn corpus_graph(
+    session: &SessionArg,
+    corpus: &str,
+    state: &GlobalAppState,
+) -> Result<AnnotationGraph> {
+    subgraph_for_query(
+        session,
+        corpus,
+        "annis:node_type=\"corpus\"",
+        Some(AnnotationComponentType::PartOf),
+        state,
+    )
+    .await
+}
+
+/// Get the graph of all nodes of a document, including the edges between them.
+/// The document is given by its fully qualified node name, e.g. `pcc2/4282`.
+pub async fn document_graph(
+    session: &SessionArg,
+    corpus: &str,
+    document: &str,
+    state: &GlobalAppState,
+) -> Result<AnnotationGraph> {
+    let query = format!(
+        "annis:node_type=\"node\" @* annis:node_name=\"{}\"",
+        document.replace('\\', "\\\\").replace('"', "\\\"")
+    );
+    subgraph_for_query(session, corpus, &query, None, state).await
+}
 
-        let (g, _config) = graphannis_core::graph::serialization::graphml::import::<
-            graphannis::model::AnnotationComponentType,
-            _,
-            _,
-        >(response_body.as_bytes(), true, |_| {})?;
+async fn subgraph_for_query(
+    session: &SessionArg,
+    corpus: &str,
+    query: &str,
+    component_type_filter: Option<AnnotationComponentType>,
+    state: &GlobalAppState,
+) -> Result<AnnotationGraph> {
+    let url = state.service_url.join(&format!(
+        "corpora/{}/subgraph-for-query",
+        utf8_percent_encode(corpus, QUERY)
+    ))?;
+    let client = state.create_client(session)?;
+
+    let query_params = SubgraphForQueryRequest {
+        query,
+        query_language: QueryLanguage::AQL,
+        component_type_filter,
+    };
+
+    let request = client
+        .request(reqwest::Method::GET, url.clone())
+        .query(&query_params)
+        .build()?;
 
-        Ok(g)
+    let response = client.execute(request).await?;
+    if response.status().is_success() {
+        let response_body = response.text().await?;
+        import_graphml(&response_body)
     } else {
         Err(AppError::Backend {
             status_code: response.status(),
@@ -85,11 +172,50 @@ struct ComponentsRequest {
 #[derive(Deserialize, Debug)]
 struct ComponentResponse {
     #[serde(rename = "type")]
-    _ctype: Option<String>,
+    ctype: AnnotationComponentType,
     name: String,
     layer: String,
 }
 
+/// List all components of a corpus, optionally filtered by their type.
+pub async fn components(
+    session: &SessionArg,
+    corpus: &str,
+    ctype: Option<AnnotationComponentType>,
+    state: &GlobalAppState,
+) -> Result<Vec<AnnotationComponent>> {
+    let url = state.service_url.join(&format!(
+        "corpora/{}/components",
+        utf8_percent_encode(corpus, QUERY)
+    ))?;
+    let client = state.create_client(session)?;
+
+    let query_params = ComponentsRequest {
+        ctype: ctype.map(|t| format!("{t:?}")),
+        name: None,
+    };
+
+    let request = client
+        .request(reqwest::Method::GET, url.clone())
+        .query(&query_params)
+        .build()?;
+
+    let response = client.execute(request).await?;
+    if response.status().is_success() {
+        let components: Vec<ComponentResponse> = response.json().await?;
+        let result = components
+            .into_iter()
+            .map(|c| AnnotationComponent::new(c.ctype, c.layer.into(), c.name.into()))
+            .collect();
+        Ok(result)
+    } else {
+        Err(AppError::Backend {
+            status_code: response.status(),
+            url: response.url().clone(),
+        })
+    }
+}
+
 /// List all segmentions (in addition to the token layer) for a given corpus.
 pub async fn segmentations(
     session: &SessionArg,
@@ -120,3 +246,293 @@ pub async fn segmentations(
         .collect();
     Ok(result)
 }
+
+#[derive(Serialize)]
+struct AnnotationsRequest {
+    list_values: bool,
+    only_most_frequent_values: bool,
+}
+
+/// List the node annotations of a corpus. If `list_values` is false, only the
+/// annotation keys are returned and the values are empty.
+pub async fn node_annotations(
+    session: &SessionArg,
+    corpus: &str,
+    list_values: bool,
+    only_most_frequent_values: bool,
+    state: &GlobalAppState,
+) -> Result<Vec<Annotation>> {
+    let url = state.service_url.join(&format!(
+        "corpora/{}/node-annotations",
+        utf8_percent_encode(corpus, QUERY)
+    ))?;
+    let client = state.create_client(session)?;
+
+    let query_params = AnnotationsRequest {
+        list_values,
+        only_most_frequent_values,
+    };
+
+    let request = client
+        .request(reqwest::Method::GET, url.clone())
+        .query(&query_params)
+        .build()?;
+
+    let response = client.execute(request).await?;
+    if response.status().is_success() {
+        let annotations = response.json().await?;
+        Ok(annotations)
+    } else {
+        Err(AppError::Backend {
+            status_code: response.status(),
+            url: response.url().clone(),
+        })
+    }
+}
+
+/// Configuration of a corpus, which is part of the corpus and defined by the
+/// corpus maintainers.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CorpusConfiguration {
+    #[serde(default)]
+    pub context: ContextConfiguration,
+    #[serde(default)]
+    pub view: ViewConfiguration,
+    #[serde(default)]
+    pub example_queries: Vec<ExampleQuery>,
+    /// Rules which visualizers to show for a match, in the order they
+    /// should be displayed.
+    #[serde(default)]
+    pub visualizers: Vec<VisualizerRule>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ContextConfiguration {
+    /// The default context size.
+    pub default: usize,
+    /// Available context sizes to choose from.
+    pub sizes: Vec<usize>,
+    /// If set, a maximum context size which should be enforced.
+    pub max: Option<usize>,
+    /// Default segmentation to use for defining the context, `None` if tokens should be used.
+    pub segmentation: Option<String>,
+}
+
+impl Default for ContextConfiguration {
+    fn default() -> Self {
+        Self {
+            default: 5,
+            sizes: vec![0, 1, 2, 5, 10],
+            max: None,
+            segmentation: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ViewConfiguration {
+    /// Default segmentation to use for the displaying the text, `None` if tokens should be used.
+    pub base_text_segmentation: Option<String>,
+    /// A list of fully qualified annotation names that should be hidden when displayed.
+    #[serde(default)]
+    pub hidden_annos: Vec<String>,
+    /// Annotation names of the corpus metadata that should be displayed
+    /// first and in the given order.
+    #[serde(default)]
+    pub corpus_annotation_order: Vec<String>,
+}
+
+/// An example query for the corpus with a description.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExampleQuery {
+    pub query: String,
+    pub description: String,
+    pub query_language: QueryLanguage,
+}
+
+/// Defines a visualizer that is shown for the matches of a corpus.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VisualizerRule {
+    /// If set, the visualizer is only shown for matches of this element type.
+    pub element: Option<VisualizerRuleElement>,
+    /// The layer (namespace) of the annotations or components to visualize.
+    pub layer: Option<String>,
+    /// The type of the visualizer, e.g. "grid" or "tree".
+    pub vis_type: String,
+    /// The name shown to the user.
+    pub display_name: String,
+    #[serde(default)]
+    pub visibility: VisualizerVisibility,
+    /// Additional parameters for the visualizer type.
+    #[serde(default)]
+    pub mappings: Option<BTreeMap<String, String>>,
+}
+
+impl VisualizerRule {
+    /// Get the value of the mapping with the given name.
+    pub fn mapping(&self, name: &str) -> Option<&str> {
+        self.mappings
+            .as_ref()
+            .and_then(|m| m.get(name))
+            .map(|v| v.as_str())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VisualizerRuleElement {
+    Node,
+    Edge,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum VisualizerVisibility {
+    /// Collapsed until the user expands it.
+    #[default]
+    Hidden,
+    /// Expanded, but can be collapsed by the user.
+    Visible,
+    /// Always expanded.
+    Permanent,
+    /// Collapsed, but already created with the match details.
+    Preloaded,
+}
+
+/// Get the configuration of a corpus.
+pub async fn configuration(
+    session: &SessionArg,
+    corpus: &str,
+    state: &GlobalAppState,
+) -> Result<CorpusConfiguration> {
+    let url = state.service_url.join(&format!(
+        "corpora/{}/configuration",
+        utf8_percent_encode(corpus, QUERY)
+    ))?;
+    let client = state.create_client(session)?;
+
+    let request = client.request(reqwest::Method::GET, url.clone()).build()?;
+
+    let response = client.execute(request).await?;
+    if response.status().is_success() {
+        let config = response.json().await?;
+        Ok(config)
+    } else {
+        Err(AppError::Backend {
+            status_code: response.status(),
+            url: response.url().clone(),
+        })
+    }
+}
+
+/// List the annotations of the edges in the given component. If
+/// `list_values` is false, only the annotation keys are returned and the
+/// values are empty.
+pub async fn edge_annotations(
+    session: &SessionArg,
+    corpus: &str,
+    component: &AnnotationComponent,
+    list_values: bool,
+    only_most_frequent_values: bool,
+    state: &GlobalAppState,
+) -> Result<Vec<Annotation>> {
+    let url = state.service_url.join(&format!(
+        "corpora/{}/edge-annotations/{:?}/{}/{}/",
+        utf8_percent_encode(corpus, QUERY),
+        component.get_type(),
+        utf8_percent_encode(&component.layer, QUERY),
+        utf8_percent_encode(&component.name, QUERY),
+    ))?;
+    let client = state.create_client(session)?;
+
+    let query_params = AnnotationsRequest {
+        list_values,
+        only_most_frequent_values,
+    };
+
+    let request = client
+        .request(reqwest::Method::GET, url.clone())
+        .query(&query_params)
+        .build()?;
+
+    let response = client.execute(request).await?;
+    if response.status().is_success() {
+        let annotations = response.json().await?;
+        Ok(annotations)
+    } else {
+        Err(AppError::Backend {
+            status_code: response.status(),
+            url: response.url().clone(),
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct ListFilesRequest<'a> {
+    node: Option<&'a str>,
+}
+
+/// List the names of the files linked to the corpus or, if given, to the
+/// (sub-) corpus or document node with the given name.
+pub async fn list_files(
+    session: &SessionArg,
+    corpus: &str,
+    node: Option<&str>,
+    state: &GlobalAppState,
+) -> Result<Vec<String>> {
+    let url = state.service_url.join(&format!(
+        "corpora/{}/files",
+        utf8_percent_encode(corpus, QUERY)
+    ))?;
+    let client = state.create_client(session)?;
+
+    let request = client
+        .request(reqwest::Method::GET, url.clone())
+        .query(&ListFilesRequest { node })
+        .build()?;
+
+    let response = client.execute(request).await?;
+    if response.status().is_success() {
+        let files = response.json().await?;
+        Ok(files)
+    } else {
+        Err(AppError::Backend {
+            status_code: response.status(),
+            url: response.url().clone(),
+        })
+    }
+}
+
+/// Request the content of a file linked to the corpus. The given `range`
+/// header is forwarded to the backend, so parts of (media) files can be
+/// requested. The response of the backend is returned as it is, so its
+/// content can be streamed.
+pub async fn file(
+    session: &SessionArg,
+    corpus: &str,
+    name: &str,
+    range: Option<&HeaderValue>,
+    state: &GlobalAppState,
+) -> Result<reqwest::Response> {
+    let url = state.service_url.join(&format!(
+        "corpora/{}/files/{}",
+        utf8_percent_encode(corpus, QUERY),
+        utf8_percent_encode(name, FILE_NAME),
+    ))?;
+    let client = state.create_client(session)?;
+
+    let mut request = client.request(reqwest::Method::GET, url.clone());
+    if let Some(range) = range {
+        request = request.header(header::RANGE, range);
+    }
+
+    let response = client.execute(request.build()?).await?;
+    if response.status().is_success() {
+        Ok(response)
+    } else {
+        Err(AppError::Backend {
+            status_code: response.status(),
+            url: response.url().clone(),
+        })
+    }
+}
@@ -1,7 +1,7 @@
 use axum::http::StatusCode;
 use futures::TryStreamExt;
-use graphannis::corpusstorage::{QueryLanguage, ResultOrder};
-use serde::Serialize;
+use graphannis::corpusstorage::{FrequencyDefEntry, FrequencyTable, QueryLanguage, ResultOrder};
+use serde::{Deserialize, Serialize};
 use std::{io::ErrorKind, mem::size_of};
 use tokio::io::AsyncBufReadExt;
 use tokio_util::io::StreamReader;
@@ -20,9 +20,112 @@ pub struct FindQuery {
     pub corpora: Vec<String>,
     pub query_language: QueryLanguage,
     pub limit: Option<u64>,
+    /// Number of matches to skip.
+    pub offset: u64,
     pub order: ResultOrder,
 }
 
+#[derive(Serialize)]
+struct ParseQueryParams<'a> {
+    query: &'a str,
+    query_language: QueryLanguage,
+}
+
+/// Description of a node in a query, as returned by the backend when parsing
+/// the query.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QueryNodeDescription {
+    /// ID of the alternative this node is part of.
+    pub alternative: usize,
+    /// Textual representation of the query fragment for this node.
+    pub query_fragment: String,
+    /// Variable name of this node.
+    pub variable: String,
+    /// Optional annotation name the node is constrained by.
+    pub anno_name: Option<String>,
+    /// Whether this node is optional.
+    pub optional: bool,
+}
+
+/// Let the backend parse the query without executing it and return a
+/// description of each node in the query. Returns an
+/// [`AppError::BackendBadRequest`] error if the query is invalid.
+pub async fn node_descriptions(
+    session: &SessionArg,
+    query: &str,
+    query_language: QueryLanguage,
+    state: &GlobalAppState,
+) -> Result<Vec<QueryNodeDescription>> {
+    let url = state.service_url.join("search/node-descriptions")?;
+    let client = state.create_client(session)?;
+
+    let request = client
+        .request(reqwest::Method::GET, url.clone())
+        .query(&ParseQueryParams {
+            query,
+            query_language,
+        })
+        .build()?;
+
+    let response = client.execute(request).await?;
+    if response.status().is_success() {
+        let result = response.json().await?;
+        Ok(result)
+    } else if response.status() == StatusCode::BAD_REQUEST {
+        let original_error: BadRequestError = response.json().await?;
+        Err(AppError::BackendBadRequest(original_error))
+    } else {
+        Err(AppError::Backend {
+            status_code: response.status(),
+            url,
+        })
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct CountQuery {
+    pub query: String,
+    pub corpora: Vec<String>,
+    pub query_language: QueryLanguage,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CountResult {
+    /// Total number of matches.
+    pub match_count: u64,
+    /// Number of documents with at least one match.
+    pub document_count: u64,
+}
+
+/// Count the matches for a given query
+pub async fn count(
+    session: &SessionArg,
+    query: &CountQuery,
+    state: &GlobalAppState,
+) -> Result<CountResult> {
+    let url = state.service_url.join("search/count")?;
+    let client = state.create_client(session)?;
+
+    let request = client
+        .request(reqwest::Method::POST, url.clone())
+        .json(&query)
+        .build()?;
+
+    let response = client.execute(request).await?;
+    if response.status().is_success() {
+        let result = response.json().await?;
+        Ok(result)
+    } else if response.status() == StatusCode::BAD_REQUEST {
+        let original_error: BadRequestError = response.json().await?;
+        Err(AppError::BackendBadRequest(original_error))
+    } else {
+        Err(AppError::Backend {
+            status_code: response.status(),
+            url,
+        })
+    }
+}
+
 /// Find all matches for a given query
 pub async fn find(
     session: &SessionArg,
@@ -68,3 +171,42 @@ pub async fn find(
         })
     }
 }
+
+#[derive(Serialize, Clone)]
+pub struct FrequencyQuery {
+    pub query: String,
+    pub corpora: Vec<String>,
+    pub query_language: QueryLanguage,
+    pub definition: Vec<FrequencyDefEntry>,
+}
+
+/// Get a frequency table for the values of the given query node annotations.
+/// The rows are sorted by their count, most frequent first.
+pub async fn frequency(
+    session: &SessionArg,
+    query: &FrequencyQuery,
+    state: &GlobalAppState,
+) -> Result<FrequencyTable<String>> {
+    let url = state.service_url.join("search/frequency")?;
+    let client = state.create_client(session)?;
+
+    let request = client
+        .request(reqwest::Method::POST, url.clone())
+        .json(&query)
+        .build()?;
+
+    let response = client.execute(request).await?;
+    if response.status().is_success() {
+        let mut result: FrequencyTable<String> = response.json().await?;
+        result.sort_by_key(|row| std::cmp::Reverse(row.count));
+        Ok(result)
+    } else if response.status() == StatusCode::BAD_REQUEST {
+        let original_error: BadRequestError = response.json().await?;
+        Err(AppError::BackendBadRequest(original_error))
+    } else {
+        Err(AppError::Backend {
+            status_code: response.status(),
+            url,
+        })
+    }
+}
@@ -0,0 +1,274 @@
+//! Collocation analysis finds the token or annotation values that occur more
+//! often in a window around the matches of a query than expected from their
+//! frequency in the whole corpus.
+
+use std::collections::HashMap;
+
+use graphannis::corpusstorage::FrequencyDefEntry;
+use graphannis_core::{graph::ANNIS_NS, util::split_qname};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr, NoneAsEmptyString};
+
+use crate::{
+    client::{
+        corpora,
+        search::{self, CountQuery, FindQuery, FrequencyQuery},
+    },
+    concordance,
+    state::{GlobalAppState, SessionArg},
+    Result,
+};
+
+/// The association measure the collocates are ordered by.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AssociationMeasure {
+    #[default]
+    LogLikelihood,
+    MutualInformation,
+    TScore,
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct CollocationConfig {
+    /// Number of token left and right of each match that are counted.
+    #[serde_as(as = "DisplayFromStr")]
+    pub window: usize,
+    /// Qualified name of the annotation to count, the token value is used if
+    /// not given.
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub annotation: Option<String>,
+    /// Only show collocates that occur at least this often in the windows.
+    #[serde_as(as = "DisplayFromStr")]
+    pub min_frequency: usize,
+    pub measure: AssociationMeasure,
+}
+
+impl Default for CollocationConfig {
+    fn default() -> Self {
+        Self {
+            window: 5,
+            annotation: None,
+            min_frequency: 2,
+            measure: AssociationMeasure::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Collocate {
+    pub value: String,
+    /// Number of occurrences in the windows around the matches.
+    pub observed: usize,
+    /// Number of occurrences in the whole corpus.
+    pub corpus_frequency: usize,
+    /// Number of occurrences in the windows expected from the corpus
+    /// frequency.
+    pub expected: f64,
+    pub log_likelihood: f64,
+    pub mutual_information: f64,
+    pub t_score: f64,
+}
+
+impl Collocate {
+    /// Calculate the association measures from the contingency table of a
+    /// value in the windows (of `window_size` token in total) and the rest of
+    /// the corpus (with `corpus_size` token in total).
+    pub fn new(
+        value: String,
+        observed: usize,
+        corpus_frequency: usize,
+        window_size: usize,
+        corpus_size: usize,
+    ) -> Self {
+        // The frequencies must be consistent, even if the corpus frequency
+        // was determined differently
+        let corpus_frequency = corpus_frequency.max(observed);
+        let corpus_size = corpus_size.max(window_size + corpus_frequency - observed);
+
+        let n = corpus_size as f64;
+        let rows = [window_size as f64, (corpus_size - window_size) as f64];
+        let columns = [
+            corpus_frequency as f64,
+            (corpus_size - corpus_frequency) as f64,
+        ];
+        let o11 = observed as f64;
+        let table = [
+            [o11, rows[0] - o11],
+            [columns[0] - o11, rows[1] - columns[0] + o11],
+        ];
+
+        let expected = rows[0] * columns[0] / n;
+
+        Self {
+            value,
+            observed,
+            corpus_frequency,
+            expected,
+            log_likelihood: log_likelihood(table),
+            mutual_information: (o11 / expected).log2(),
+            t_score: (o11 - expected) / o11.sqrt(),
+        }
+    }
+
+    fn measure(&self, measure: AssociationMeasure) -> f64 {
+        match measure {
+            AssociationMeasure::LogLikelihood => self.log_likelihood,
+            AssociationMeasure::MutualInformation => self.mutual_information,
+            AssociationMeasure::TScore => self.t_score,
+        }
+    }
+}
+
+/// Calculate the log-likelihood ratio (G²) of a 2×2 contingency table,
+/// comparing the observed frequencies with the ones expected from the row and
+/// column totals.
+pub fn log_likelihood(table: [[f64; 2]; 2]) -> f64 {
+    let rows = [table[0][0] + table[0][1], table[1][0] + table[1][1]];
+    let columns = [table[0][0] + table[1][0], table[0][1] + table[1][1]];
+    let n = rows[0] + rows[1];
+
+    let mut result = 0.0;
+    for (i, row) in table.iter().enumerate() {
+        for (j, o) in row.iter().enumerate() {
+            let e = rows[i] * columns[j] / n;
+            if *o > 0.0 && e > 0.0 {
+                result += o * (o / e).ln();
+            }
+        }
+    }
+    2.0 * result
+}
+
+/// Maximum number of matches whose windows are counted. The subgraph of each
+/// match is needed, so only the first matches of larger results are analyzed.
+pub const MAX_ANALYZED_MATCHES: usize = 1000;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Collocations {
+    /// Number of analyzed matches.
+    pub match_count: usize,
+    /// Whether the query has more matches than [`MAX_ANALYZED_MATCHES`],
+    /// which were not analyzed.
+    pub limited: bool,
+    /// Number of token in all windows.
+    pub window_size: usize,
+    /// Number of token in the selected corpora.
+    pub corpus_size: usize,
+    /// The collocates, ordered by the configured association measure.
+    pub collocates: Vec<Collocate>,
+}
+
+/// Count the values in the window around the first [`MAX_ANALYZED_MATCHES`]
+/// matches of the query and compare them with their frequency in the
+/// selected corpora.
+pub async fn analyze(
+    session: &SessionArg,
+    query: &FindQuery,
+    config: &CollocationConfig,
+    state: &GlobalAppState,
+) -> Result<Collocations> {
+    let annotation = config.annotation.as_deref();
+
+    // Count the values in the windows. One additional match is requested to
+    // know whether there are more matches than analyzed.
+    let mut limited_query = query.clone();
+    limited_query.limit = Some(MAX_ANALYZED_MATCHES as u64 + 1);
+    limited_query.offset = 0;
+    let matches = search::find(session, &limited_query, state).await?;
+    let limited = matches.len() > MAX_ANALYZED_MATCHES;
+    let mut observed: HashMap<String, usize> = HashMap::new();
+    let mut window_size = 0;
+    for m in matches.range(..)?.take(MAX_ANALYZED_MATCHES) {
+        let (_, node_ids) = m?;
+        let corpus = node_ids
+            .first()
+            .and_then(|id| id.split_once('/'))
+            .map(|(corpus, _)| corpus)
+            .unwrap_or_default();
+        let g = corpora::subgraph(
+            session,
+            corpus,
+            node_ids.clone(),
+            None,
+            config.window,
+            config.window,
+            state,
+        )
+        .await?;
+        let kwic = concordance::kwic_tokens(&g, &node_ids, None)?;
+        let window = kwic
+            .left
+            .iter()
+            .rev()
+            .take(config.window)
+            .chain(kwic.right.iter().take(config.window));
+        for t in window {
+            window_size += 1;
+            let value = concordance::token_value(&g, *t, annotation)?;
+            if !value.is_empty() {
+                *observed.entry(value).or_default() += 1;
+            }
+        }
+    }
+
+    // Get the frequency of all values and the number of token in the corpora
+    let (ns, name) = match annotation {
+        Some(annotation) => split_qname(annotation),
+        None => (Some(ANNIS_NS), "tok"),
+    };
+    let frequency_query = FrequencyQuery {
+        query: annotation.unwrap_or("tok").to_string(),
+        corpora: query.corpora.clone(),
+        query_language: query.query_language,
+        definition: vec![FrequencyDefEntry {
+            ns: ns.map(str::to_string),
+            name: name.to_string(),
+            node_ref: "1".to_string(),
+        }],
+    };
+    let corpus_frequency: HashMap<String, usize> =
+        search::frequency(session, &frequency_query, state)
+            .await?
+            .into_iter()
+            .filter_map(|row| Some((row.values.into_iter().next()?, row.count)))
+            .collect();
+    let token_count = search::count(
+        session,
+        &CountQuery {
+            query: "tok".to_string(),
+            corpora: query.corpora.clone(),
+            query_language: query.query_language,
+        },
+        state,
+    )
+    .await?;
+    let corpus_size = token_count.match_count as usize;
+
+    let mut collocates: Vec<Collocate> = observed
+        .into_iter()
+        .filter(|(_, count)| *count >= config.min_frequency)
+        .map(|(value, count)| {
+            let frequency = corpus_frequency.get(&value).copied().unwrap_or_default();
+            Collocate::new(value, count, frequency, window_size, corpus_size)
+        })
+        .collect();
+    collocates.sort_by(|a, b| {
+        b.measure(config.measure)
+            .total_cmp(&a.measure(config.measure))
+            .then_with(|| a.value.cmp(&b.value))
+    });
+
+    Ok(Collocations {
+        match_count: matches.len().min(MAX_ANALYZED_MATCHES),
+        limited,
+        window_size,
+        corpus_size,
+        collocates,
+    })
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,26 @@
+use super::Collocate;
+
+#[test]
+fn association_measures() {
+    // 10 of 100 token in the windows and 20 of 1000 token in the corpus
+    let c = Collocate::new("a".to_string(), 10, 20, 100, 1000);
+    assert_eq!(2.0, c.expected);
+    assert!((c.mutual_information - 5.0_f64.log2()).abs() < 1e-9);
+    assert!((c.t_score - 8.0 / 10.0_f64.sqrt()).abs() < 1e-9);
+    assert!((c.log_likelihood - 21.176963409906).abs() < 1e-9);
+
+    // A value that is not more frequent than expected
+    let c = Collocate::new("b".to_string(), 2, 20, 100, 1000);
+    assert_eq!(0.0, c.mutual_information);
+    assert_eq!(0.0, c.t_score);
+    assert!(c.log_likelihood.abs() < 1e-9);
+}
+
+#[test]
+fn inconsistent_frequencies_are_corrected() {
+    // The value is more often in the windows than in the whole corpus
+    let c = Collocate::new("a".to_string(), 4, 2, 10, 8);
+    assert_eq!(4, c.corpus_frequency);
+    assert!(c.log_likelihood.is_finite());
+    assert!(c.mutual_information.is_finite());
+}
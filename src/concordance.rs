@@ -0,0 +1,326 @@
+//! A concordance shows the matches with their left and right context as
+//! keyword in context (KWIC) lines, which can be sorted by the token or
+//! annotation values at a position left or right of the match.
+
+use std::{collections::BTreeSet, fmt::Display, str::FromStr, sync::Arc};
+
+use graphannis::{
+    corpusstorage::QueryLanguage,
+    graph::{AnnoKey, GraphStorage, NodeID},
+    model::AnnotationComponentType,
+    AnnotationGraph,
+};
+use graphannis_core::{graph::ANNIS_NS, util::split_qname};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::{
+        corpora,
+        search::{self, FindQuery},
+    },
+    converter::{ordered_tokens, CSVConfig},
+    state::{GlobalAppState, SessionArg},
+    visualizer::gap_edges,
+    Result,
+};
+
+/// A token position in the left (L1 to L3) or right (R1 to R3) context of a
+/// match, counted from the match.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextPosition {
+    L3,
+    L2,
+    L1,
+    R1,
+    R2,
+    R3,
+}
+
+impl ContextPosition {
+    pub const ALL: [ContextPosition; 6] = [
+        ContextPosition::L3,
+        ContextPosition::L2,
+        ContextPosition::L1,
+        ContextPosition::R1,
+        ContextPosition::R2,
+        ContextPosition::R3,
+    ];
+
+    fn is_left(self) -> bool {
+        matches!(
+            self,
+            ContextPosition::L1 | ContextPosition::L2 | ContextPosition::L3
+        )
+    }
+
+    /// Distance to the match, starting with 1 for the next token.
+    fn distance(self) -> usize {
+        match self {
+            ContextPosition::L1 | ContextPosition::R1 => 1,
+            ContextPosition::L2 | ContextPosition::R2 => 2,
+            ContextPosition::L3 | ContextPosition::R3 => 3,
+        }
+    }
+}
+
+impl Display for ContextPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let side = if self.is_left() { 'L' } else { 'R' };
+        write!(f, "{side}{}", self.distance())
+    }
+}
+
+impl FromStr for ContextPosition {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        ContextPosition::ALL
+            .into_iter()
+            .find(|p| p.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown context position {s}"))
+    }
+}
+
+/// The token (or segmentation nodes) of a match subgraph, split into the
+/// left context, the matched token and the right context.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KwicTokens {
+    pub left: Vec<NodeID>,
+    pub matched: Vec<NodeID>,
+    pub right: Vec<NodeID>,
+}
+
+/// Split the token of the subgraph for the match with the given node names.
+/// Everything from the first to the last token overlapping with a matched
+/// node belongs to the match.
+pub fn kwic_tokens(
+    g: &AnnotationGraph,
+    node_names: &[String],
+    segmentation: Option<&str>,
+) -> Result<KwicTokens> {
+    let tokens = ordered_tokens(g, segmentation, &gap_edges(g)?)?;
+    let coverage: Vec<Arc<dyn GraphStorage>> = g
+        .get_all_components(Some(AnnotationComponentType::Coverage), None)
+        .into_iter()
+        .filter_map(|c| g.get_graphstorage(&c))
+        .collect();
+    let covered = |node: NodeID| -> Result<BTreeSet<NodeID>> {
+        let mut result = BTreeSet::from([node]);
+        for gs in coverage.iter() {
+            for t in gs.get_outgoing_edges(node) {
+                result.insert(t?);
+            }
+        }
+        Ok(result)
+    };
+
+    let mut matched_token = BTreeSet::new();
+    for name in node_names {
+        if let Some(node) = g.get_node_id_from_name(name)? {
+            matched_token.extend(covered(node)?);
+        }
+    }
+    let mut matched_positions = Vec::new();
+    for (idx, t) in tokens.iter().enumerate() {
+        if !covered(t.node)?.is_disjoint(&matched_token) {
+            matched_positions.push(idx);
+        }
+    }
+
+    let nodes: Vec<NodeID> = tokens.into_iter().map(|t| t.node).collect();
+    if let (Some(first), Some(last)) = (matched_positions.first(), matched_positions.last()) {
+        Ok(KwicTokens {
+            left: nodes[..*first].to_vec(),
+            matched: nodes[*first..=*last].to_vec(),
+            right: nodes[last + 1..].to_vec(),
+        })
+    } else {
+        Ok(KwicTokens::default())
+    }
+}
+
+/// Get the text of the given token, separated by their whitespace.
+pub fn token_text(g: &AnnotationGraph, token: &[NodeID]) -> Result<String> {
+    let token_value_key = AnnoKey {
+        ns: ANNIS_NS.into(),
+        name: "tok".into(),
+    };
+    let whitespace_after_key = AnnoKey {
+        ns: ANNIS_NS.into(),
+        name: "tok-whitespace-after".into(),
+    };
+    let mut result = String::new();
+    for (idx, t) in token.iter().enumerate() {
+        if let Some(value) = g.get_node_annos().get_value_for_item(t, &token_value_key)? {
+            result.push_str(&value);
+        }
+        if idx + 1 < token.len() {
+            match g
+                .get_node_annos()
+                .get_value_for_item(t, &whitespace_after_key)?
+            {
+                Some(ws) => result.push_str(&ws),
+                None => result.push(' '),
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Get the value to sort by for the token at the given position. This is the
+/// token value or the value of the given annotation, see [`token_value`].
+/// Returns an empty string if the context does not include the position.
+pub fn sort_value(
+    g: &AnnotationGraph,
+    kwic: &KwicTokens,
+    position: ContextPosition,
+    annotation: Option<&str>,
+) -> Result<String> {
+    let token = if position.is_left() {
+        kwic.left
+            .len()
+            .checked_sub(position.distance())
+            .and_then(|idx| kwic.left.get(idx))
+    } else {
+        kwic.right.get(position.distance() - 1)
+    };
+    if let Some(token) = token {
+        token_value(g, *token, annotation)
+    } else {
+        Ok(String::default())
+    }
+}
+
+/// Get the text of a token or, if an annotation name is given, the value of
+/// this annotation on the token or on a span covering it. Returns an empty
+/// string if there is no such annotation.
+pub fn token_value(g: &AnnotationGraph, token: NodeID, annotation: Option<&str>) -> Result<String> {
+    let Some(annotation) = annotation.filter(|a| !a.is_empty()) else {
+        return token_text(g, &[token]);
+    };
+    let (ns, name) = split_qname(annotation);
+    let mut candidates = vec![token];
+    for c in g.get_all_components(Some(AnnotationComponentType::Coverage), None) {
+        if let Some(gs) = g.get_graphstorage(&c) {
+            for span in gs.get_ingoing_edges(token) {
+                candidates.push(span?);
+            }
+        }
+    }
+    for node in candidates {
+        for anno in g.get_node_annos().get_annotations_for_item(&node)? {
+            if anno.key.name == name && ns.map_or(anno.key.ns != ANNIS_NS, |ns| anno.key.ns == ns) {
+                return Ok(anno.val.to_string());
+            }
+        }
+    }
+    Ok(String::default())
+}
+
+/// Sort the entries by their (case-insensitive) sort value and keep the order
+/// of matches with the same value. Empty values are sorted last.
+pub fn sort_by_value<T>(entries: &mut [(String, T)]) {
+    entries.sort_by_cached_key(|(value, _)| (value.is_empty(), value.to_lowercase()));
+}
+
+/// Maximum number of matches that are sorted by their context. Sorting needs
+/// the subgraph of each match, so only the first matches of larger results
+/// are sorted.
+pub const MAX_SORTED_MATCHES: usize = 1000;
+
+/// The matches of a query in the order given by the sort position of the
+/// configuration. This is cached for each session, so paging through the
+/// sorted matches does not need to fetch all subgraphs again.
+#[derive(Debug, Clone)]
+pub struct SortedMatches {
+    query: String,
+    query_language: QueryLanguage,
+    corpora: Vec<String>,
+    config: CSVConfig,
+    /// Match number and node names of each match.
+    pub matches: Arc<Vec<(u64, Vec<String>)>>,
+}
+
+/// Find the first [`MAX_SORTED_MATCHES`] matches of the query and sort them
+/// by the given position. The subgraph with the configured context is
+/// fetched for each match.
+pub async fn sorted_matches(
+    session: &SessionArg,
+    query: &FindQuery,
+    position: ContextPosition,
+    config: &CSVConfig,
+    state: &GlobalAppState,
+) -> Result<Arc<Vec<(u64, Vec<String>)>>> {
+    if let Some(cached) = state.sorted_matches.get(&session.id()) {
+        if cached.query == query.query
+            && std::mem::discriminant(&cached.query_language)
+                == std::mem::discriminant(&query.query_language)
+            && cached.corpora == query.corpora
+            && cached.config == *config
+        {
+            return Ok(cached.matches.clone());
+        }
+    }
+
+    let mut query = query.clone();
+    query.limit = Some(MAX_SORTED_MATCHES as u64);
+    query.offset = 0;
+    let result = search::find(session, &query, state).await?;
+    let mut entries = Vec::with_capacity(result.len());
+    for m in result.range(..)? {
+        let (match_nr, node_ids) = m?;
+        let corpus = node_ids
+            .first()
+            .and_then(|id| id.split_once('/'))
+            .map(|(corpus, _)| corpus)
+            .unwrap_or_default();
+        let g = corpora::subgraph(
+            session,
+            corpus,
+            node_ids.clone(),
+            config.span_segmentation.clone(),
+            config.left_context,
+            config.right_context,
+            state,
+        )
+        .await?;
+        let kwic = kwic_tokens(&g, &node_ids, config.span_segmentation.as_deref())?;
+        let value = sort_value(&g, &kwic, position, config.sort_annotation.as_deref())?;
+        entries.push((value, (match_nr, node_ids)));
+    }
+    sort_by_value(&mut entries);
+
+    let matches = Arc::new(entries.into_iter().map(|(_, m)| m).collect::<Vec<_>>());
+    state.sorted_matches.insert(
+        session.id(),
+        SortedMatches {
+            query: query.query,
+            query_language: query.query_language,
+            corpora: query.corpora,
+            config: config.clone(),
+            matches: matches.clone(),
+        },
+    );
+    Ok(matches)
+}
+
+/// A match with its left and right context as text.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct KwicLine {
+    /// Number of the match in the unsorted result.
+    pub match_nr: u64,
+    pub left: String,
+    pub matched: String,
+    pub right: String,
+}
+
+impl KwicLine {
+    pub fn new(g: &AnnotationGraph, match_nr: u64, kwic: &KwicTokens) -> Result<Self> {
+        Ok(Self {
+            match_nr,
+            left: token_text(g, &kwic.left)?,
+            matched: token_text(g, &kwic.matched)?,
+            right: token_text(g, &kwic.right)?,
+        })
+    }
+}
@@ -23,12 +23,13 @@ use crate::{
         corpora,
         search::{self, FindQuery},
     },
+    concordance::{self, ContextPosition},
     state::{GlobalAppState, SessionArg},
     Result,
 };
 
 #[serde_as]
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
 pub struct CSVConfig {
     #[serde(default)]
     #[serde_as(as = "NoneAsEmptyString")]
@@ -39,6 +40,15 @@ pub struct CSVConfig {
     #[serde(default)]
     #[serde_as(as = "DisplayFromStr")]
     pub right_context: usize,
+    /// Sort the matches by the value at this position of the context.
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub sort_position: Option<ContextPosition>,
+    /// Qualified name of the annotation to sort by, the token value is used
+    /// if not given.
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub sort_annotation: Option<String>,
 }
 
 pub struct CSVExporter {
@@ -178,9 +188,34 @@ impl CSVExporter {
             writer.write_record(header)?;
         }
 
-        // Iterate over all matches
+        // Collect all matches and sort them by their context if configured
+        let mut sorted_matches = Vec::with_capacity(matches.len());
         for m in matches.range(..)? {
             let (idx, node_ids) = m?;
+            let value = match (self.config.sort_position, self.subgraphs.get(&idx)) {
+                (Some(position), Some(g)) => {
+                    let kwic = concordance::kwic_tokens(
+                        g,
+                        &node_ids,
+                        self.config.span_segmentation.as_deref(),
+                    )?;
+                    concordance::sort_value(
+                        g,
+                        &kwic,
+                        position,
+                        self.config.sort_annotation.as_deref(),
+                    )?
+                }
+                _ => String::default(),
+            };
+            sorted_matches.push((value, (idx, node_ids)));
+        }
+        if self.config.sort_position.is_some() {
+            concordance::sort_by_value(&mut sorted_matches);
+        }
+
+        // Iterate over all matches
+        for (written, (_, (idx, node_ids))) in sorted_matches.into_iter().enumerate() {
             // Get the subgraph for the IDs
             if let Some(g) = self.subgraphs.get(&idx) {
                 let mut record: Vec<String> = Vec::with_capacity(node_ids.len() + 1);
@@ -202,9 +237,9 @@ impl CSVExporter {
                 writer.write_record(record)?;
             }
 
-            if idx % 10 == 0 {
+            if written % 10 == 0 {
                 if let Some(sender) = &self.progress {
-                    let partial_progress = idx as f32 / matches.len() as f32;
+                    let partial_progress = written as f32 / matches.len() as f32;
                     sender
                         .send(AFTER_FIRST_PASS_PROGRESS + (partial_progress * SINGLE_PASS_PROGRESS))
                         .await?;
@@ -215,80 +250,8 @@ impl CSVExporter {
     }
 
     fn get_spannd_text(&self, g: &AnnotationGraph) -> Result<String> {
-        // Get ordering component that matches the configured segmentation
-        let ordering_component = if let Some(seg) = &self.config.span_segmentation {
-            Component::new(
-                AnnotationComponentType::Ordering,
-                "default_ns".into(),
-                seg.into(),
-            )
-        } else {
-            Component::new(
-                AnnotationComponentType::Ordering,
-                ANNIS_NS.into(),
-                "".into(),
-            )
-        };
-
-        let filtering_anno_key = self.config.span_segmentation.as_ref().map(|seg| AnnoKey {
-            name: seg.into(),
-            ns: "default_ns".into(),
-        });
-
-        let ordering_gs = g.get_graphstorage_as_ref(&ordering_component);
-        let cov_edges: Vec<Arc<dyn GraphStorage>> = g
-            .get_all_components(Some(AnnotationComponentType::Coverage), None)
-            .into_iter()
-            .filter_map(|c| g.get_graphstorage(&c))
-            .filter(|gs| {
-                if let Some(stats) = gs.get_statistics() {
-                    stats.nodes > 0
-                } else {
-                    true
-                }
-            })
-            .collect();
+        let tokens = ordered_tokens(g, self.config.span_segmentation.as_deref(), &self.gap_edges)?;
 
-        let mut roots: HashSet<_> = HashSet::new();
-        for n in g
-            .get_node_annos()
-            .exact_anno_search(Some(ANNIS_NS), "tok", ValueSearch::Any)
-        {
-            let n = n?;
-
-            let has_anno = if let Some(filter) = &filtering_anno_key {
-                // For segmentation search, only include the nodes that have a matching annotation
-                g.get_node_annos()
-                    .get_value_for_item(&n.node, filter)?
-                    .is_some()
-            } else {
-                // Check that this is an actual token and there are no outgoing coverage edges
-                let mut actual_token = true;
-                for c in cov_edges.iter() {
-                    if c.has_outgoing_edges(n.node)? {
-                        actual_token = false;
-                        break;
-                    }
-                }
-                actual_token
-            };
-
-            if has_anno
-                && (ordering_gs.is_none()
-                    || ordering_gs.is_some_and(|gs| gs.get_ingoing_edges(n.node).next().is_none()))
-            {
-                roots.insert(n.node);
-            }
-        }
-
-        // Order the roots in the overall text position by using the
-        // explicit gap edges. First find the root node that has no incoming
-        // gap, than follow the ordering and gap edges and construct the
-        // text in between.
-        let mut result = String::new();
-        let mut token = roots
-            .into_iter()
-            .find(|r| !self.gap_edges.contains_right(r));
         let token_value_key = AnnoKey {
             ns: ANNIS_NS.into(),
             name: "tok".into(),
@@ -302,14 +265,15 @@ impl CSVExporter {
             name: "tok-whitespace-after".into(),
         };
 
+        let mut result = String::new();
+        let mut tokens = tokens.into_iter().peekable();
         let mut is_first_token = true;
-
-        while let Some(current_token) = token {
+        while let Some(current_token) = tokens.next() {
             // Add prefix whitespace only for first token
             if is_first_token {
                 if let Some(val) = g
                     .get_node_annos()
-                    .get_value_for_item(&current_token, &whitespace_before_key)?
+                    .get_value_for_item(&current_token.node, &whitespace_before_key)?
                 {
                     result.push_str(&val);
                 }
@@ -317,33 +281,21 @@ impl CSVExporter {
 
             if let Some(val) = g
                 .get_node_annos()
-                .get_value_for_item(&current_token, &token_value_key)?
+                .get_value_for_item(&current_token.node, &token_value_key)?
             {
                 result.push_str(&val);
             }
 
             is_first_token = false;
 
-            // Try to get the outgoing ordering edge first
-            token = if let Some(ordering_gs) = ordering_gs {
-                if let Some(next_token) = ordering_gs.get_outgoing_edges(current_token).next() {
-                    let next_token = next_token?;
-                    Some(next_token)
-                } else if let Some(next_token) = self.gap_edges.get_by_left(&current_token) {
+            // Add postfix whitespace (but not for the last token)
+            if let Some(next_token) = tokens.peek() {
+                if next_token.after_gap {
                     result.push_str("(...) ");
-                    Some(*next_token)
-                } else {
-                    None
                 }
-            } else {
-                None
-            };
-
-            // Add postfix whitespace (but not for the last token)
-            if token.is_some() {
                 if let Some(val) = g
                     .get_node_annos()
-                    .get_value_for_item(&current_token, &whitespace_after_key)?
+                    .get_value_for_item(&current_token.node, &whitespace_after_key)?
                 {
                     result.push_str(&val);
                 } else if self.config.span_segmentation.is_some() {
@@ -356,3 +308,126 @@ impl CSVExporter {
         Ok(result)
     }
 }
+
+/// A token (or segmentation node) in the order of the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct OrderedToken {
+    pub node: NodeID,
+    /// True if there is a gap in the text between this and the previous token,
+    /// e.g. because the context of the match does not include the tokens in
+    /// between.
+    pub after_gap: bool,
+}
+
+/// Get the tokens of the graph in the order of the text. If a segmentation is
+/// given, the nodes of this segmentation are returned instead of the token.
+/// The `gap_edges` connect the last token before a gap with the first token
+/// after it.
+pub(crate) fn ordered_tokens(
+    g: &AnnotationGraph,
+    segmentation: Option<&str>,
+    gap_edges: &bimap::BiHashMap<NodeID, NodeID>,
+) -> Result<Vec<OrderedToken>> {
+    // Get ordering component that matches the configured segmentation
+    let ordering_component = if let Some(seg) = segmentation {
+        Component::new(
+            AnnotationComponentType::Ordering,
+            "default_ns".into(),
+            seg.into(),
+        )
+    } else {
+        Component::new(
+            AnnotationComponentType::Ordering,
+            ANNIS_NS.into(),
+            "".into(),
+        )
+    };
+
+    let filtering_anno_key = segmentation.map(|seg| AnnoKey {
+        name: seg.into(),
+        ns: "default_ns".into(),
+    });
+
+    let ordering_gs = g.get_graphstorage_as_ref(&ordering_component);
+    let cov_edges: Vec<Arc<dyn GraphStorage>> = g
+        .get_all_components(Some(AnnotationComponentType::Coverage), None)
+        .into_iter()
+        .filter_map(|c| g.get_graphstorage(&c))
+        .filter(|gs| {
+            if let Some(stats) = gs.get_statistics() {
+                stats.nodes > 0
+            } else {
+                true
+            }
+        })
+        .collect();
+
+    let mut roots: HashSet<_> = HashSet::new();
+    for n in g
+        .get_node_annos()
+        .exact_anno_search(Some(ANNIS_NS), "tok", ValueSearch::Any)
+    {
+        let n = n?;
+
+        let has_anno = if let Some(filter) = &filtering_anno_key {
+            // For segmentation search, only include the nodes that have a matching annotation
+            g.get_node_annos()
+                .get_value_for_item(&n.node, filter)?
+                .is_some()
+        } else {
+            // Check that this is an actual token and there are no outgoing coverage edges
+            let mut actual_token = true;
+            for c in cov_edges.iter() {
+                if c.has_outgoing_edges(n.node)? {
+                    actual_token = false;
+                    break;
+                }
+            }
+            actual_token
+        };
+
+        if has_anno
+            && (ordering_gs.is_none()
+                || ordering_gs.is_some_and(|gs| gs.get_ingoing_edges(n.node).next().is_none()))
+        {
+            roots.insert(n.node);
+        }
+    }
+
+    // Order the roots in the overall text position by using the
+    // explicit gap edges. First find the root node that has no incoming
+    // gap, than follow the ordering and gap edges.
+    let mut result = Vec::new();
+    let mut token = roots
+        .into_iter()
+        .find(|r| !gap_edges.contains_right(r))
+        .map(|node| OrderedToken {
+            node,
+            after_gap: false,
+        });
+
+    while let Some(current_token) = token {
+        result.push(current_token);
+
+        // Try to get the outgoing ordering edge first
+        token = if let Some(ordering_gs) = ordering_gs {
+            if let Some(next_token) = ordering_gs.get_outgoing_edges(current_token.node).next() {
+                Some(OrderedToken {
+                    node: next_token?,
+                    after_gap: false,
+                })
+            } else {
+                gap_edges
+                    .get_by_left(&current_token.node)
+                    .map(|next_token| OrderedToken {
+                        node: *next_token,
+                        after_gap: true,
+                    })
+            }
+        } else {
+            None
+        };
+    }
+
+    Ok(result)
+}
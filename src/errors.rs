@@ -63,7 +63,6 @@ impl Display for AQLError {
 
 #[derive(Deserialize, Debug)]
 #[non_exhaustive]
-
 pub enum BadRequestError {
     AQLSyntaxError(AQLError),
     AQLSemanticError(AQLError),
@@ -72,6 +71,19 @@ pub enum BadRequestError {
     IllegalNodePath(String),
 }
 
+impl BadRequestError {
+    /// Returns the location of the error in the query, if this is an error
+    /// about the query and the location is known.
+    pub fn query_location(&self) -> Option<&LineColumnRange> {
+        match self {
+            BadRequestError::AQLSyntaxError(e) | BadRequestError::AQLSemanticError(e) => {
+                e.location.as_ref()
+            }
+            _ => None,
+        }
+    }
+}
+
 impl Display for BadRequestError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -119,6 +131,14 @@ pub enum AppError {
     InvalidHeaderValue(#[from] InvalidHeaderValue),
     #[error("Download file not found.")]
     DownloadFileNotFound,
+    #[error("Match number {0} not found.")]
+    MatchNotFound(u64),
+    #[error("Saved query {0} not found.")]
+    SavedQueryNotFound(String),
+    #[error("You need to be logged in for this action.")]
+    LoginRequired,
+    #[error("Only the owner of the saved query can change it.")]
+    NotOwner,
     #[error(transparent)]
     MiniJinja(#[from] minijinja::Error),
     #[error(transparent)]
@@ -144,6 +164,8 @@ pub enum AppError {
     },
     #[error(transparent)]
     ChronoOutOfRangeError(#[from] OutOfRangeError),
+    #[error(transparent)]
+    ChronoParseError(#[from] chrono::ParseError),
 }
 
 impl From<(http::StatusCode, &'static str)> for AppError {
@@ -161,6 +183,10 @@ impl IntoResponse for AppError {
         let (status, message) = match self {
             AppError::Reqwest(e) => (StatusCode::BAD_GATEWAY, format!("{}", e)),
             AppError::DownloadFileNotFound => (StatusCode::NOT_FOUND, format!("{}", &self)),
+            AppError::MatchNotFound(_) => (StatusCode::NOT_FOUND, format!("{}", &self)),
+            AppError::SavedQueryNotFound(_) => (StatusCode::NOT_FOUND, format!("{}", &self)),
+            AppError::LoginRequired => (StatusCode::UNAUTHORIZED, format!("{}", &self)),
+            AppError::NotOwner => (StatusCode::FORBIDDEN, format!("{}", &self)),
             AppError::Backend { .. } => (StatusCode::BAD_GATEWAY, format!("{}", &self)),
             AppError::UrlParsing(e) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
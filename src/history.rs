@@ -0,0 +1,105 @@
+//! The history of executed queries. It is stored in the session and, if a
+//! user is logged in, in the [`crate::user_store::UserStore`] so it is
+//! available in all sessions of the user.
+
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Utc};
+use graphannis::corpusstorage::QueryLanguage;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    converter::CSVConfig,
+    state::{GlobalAppState, Session},
+    Result,
+};
+
+/// Maximum number of entries kept in the history.
+pub const MAX_HISTORY_ENTRIES: usize = 50;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryEntry {
+    pub query: String,
+    pub query_language: QueryLanguage,
+    pub corpora: Vec<String>,
+    /// Context and segmentation the matches were shown with, so the query
+    /// can be re-run with the same settings.
+    #[serde(default)]
+    pub left_context: usize,
+    #[serde(default)]
+    pub right_context: usize,
+    #[serde(default)]
+    pub segmentation: Option<String>,
+    pub executed: DateTime<Utc>,
+    /// Number of matches, if it was known when the query was executed.
+    pub match_count: Option<u64>,
+}
+
+impl HistoryEntry {
+    /// Create an entry for a query that has been executed right now.
+    pub fn new(
+        query: &str,
+        query_language: QueryLanguage,
+        config: &CSVConfig,
+        corpora: &BTreeSet<String>,
+        match_count: Option<u64>,
+    ) -> Self {
+        Self {
+            query: query.to_string(),
+            query_language,
+            corpora: corpora.iter().cloned().collect(),
+            left_context: config.left_context,
+            right_context: config.right_context,
+            segmentation: config.span_segmentation.clone(),
+            executed: Utc::now(),
+            match_count,
+        }
+    }
+
+    /// Whether both entries are the same query on the same corpora.
+    pub fn is_same_query(&self, other: &HistoryEntry) -> bool {
+        self.query == other.query
+            && std::mem::discriminant(&self.query_language)
+                == std::mem::discriminant(&other.query_language)
+            && self.corpora == other.corpora
+    }
+}
+
+/// Add the entry in front of the history, replacing older entries of the
+/// same query.
+fn add_entry(history: &mut Vec<HistoryEntry>, entry: HistoryEntry) {
+    history.retain(|e| !e.is_same_query(&entry));
+    history.insert(0, entry);
+    history.truncate(MAX_HISTORY_ENTRIES);
+}
+
+/// Record an executed query in the history of the session and of the logged
+/// in user. Empty queries are ignored.
+pub async fn record(session: &Session, entry: HistoryEntry, state: &GlobalAppState) -> Result<()> {
+    if entry.query.trim().is_empty() {
+        return Ok(());
+    }
+    if let Some(user_id) = state.user_id(session)? {
+        state
+            .user_store
+            .add_history_entry(&user_id, &entry, MAX_HISTORY_ENTRIES)
+            .await?;
+    }
+    let mut history = session.query_history()?;
+    add_entry(&mut history, entry);
+    session.set_query_history(history)?;
+    Ok(())
+}
+
+/// The history of the logged in user or, if no user is logged in, of the
+/// session. The most recent entry comes first.
+pub async fn entries(session: &Session, state: &GlobalAppState) -> Result<Vec<HistoryEntry>> {
+    if let Some(user_id) = state.user_id(session)? {
+        state.user_store.history(&user_id).await
+    } else {
+        session.query_history()
+    }
+}
+
+#[cfg(test)]
+mod tests;
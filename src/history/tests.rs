@@ -0,0 +1,143 @@
+use std::collections::BTreeSet;
+
+use graphannis::corpusstorage::QueryLanguage;
+use pretty_assertions::assert_eq;
+use test_log::test;
+
+use super::{add_entry, HistoryEntry, MAX_HISTORY_ENTRIES};
+use crate::{converter::CSVConfig, user_store::UserStore};
+
+fn entry(query: &str, corpora: &[&str], match_count: Option<u64>) -> HistoryEntry {
+    let corpora: BTreeSet<String> = corpora.iter().map(|c| c.to_string()).collect();
+    HistoryEntry::new(
+        query,
+        QueryLanguage::AQL,
+        &CSVConfig::default(),
+        &corpora,
+        match_count,
+    )
+}
+
+fn queries(history: &[HistoryEntry]) -> Vec<(&str, Option<u64>)> {
+    history
+        .iter()
+        .map(|e| (e.query.as_str(), e.match_count))
+        .collect()
+}
+
+#[test]
+fn repeated_queries_are_moved_to_the_front() {
+    let mut history = Vec::new();
+    add_entry(&mut history, entry("tok", &["pcc2"], Some(10)));
+    add_entry(&mut history, entry("pos", &["pcc2"], Some(3)));
+    // Same query on other corpora is a different entry
+    add_entry(&mut history, entry("tok", &["RIDGES"], Some(7)));
+    add_entry(&mut history, entry("tok", &["pcc2"], Some(11)));
+    assert_eq!(
+        vec![("tok", Some(11)), ("tok", Some(7)), ("pos", Some(3))],
+        queries(&history)
+    );
+
+    for i in 0..MAX_HISTORY_ENTRIES {
+        add_entry(&mut history, entry(&format!("tok{i}"), &["pcc2"], None));
+    }
+    assert_eq!(MAX_HISTORY_ENTRIES, history.len());
+    assert_eq!(format!("tok{}", MAX_HISTORY_ENTRIES - 1), history[0].query);
+}
+
+#[test(tokio::test)]
+async fn user_store_history() {
+    let store = UserStore::new(None).unwrap();
+    store.migrate().await.unwrap();
+
+    store
+        .add_history_entry("user1", &entry("tok", &["pcc2"], Some(10)), 2)
+        .await
+        .unwrap();
+    store
+        .add_history_entry("user1", &entry("pos", &["pcc2", "RIDGES"], None), 2)
+        .await
+        .unwrap();
+    store
+        .add_history_entry("user2", &entry("lemma", &["pcc2"], Some(1)), 2)
+        .await
+        .unwrap();
+    store
+        .add_history_entry("user1", &entry("tok", &["pcc2"], Some(11)), 2)
+        .await
+        .unwrap();
+
+    let history = store.history("user1").await.unwrap();
+    assert_eq!(vec![("tok", Some(11)), ("pos", None)], queries(&history));
+    assert_eq!(vec!["RIDGES", "pcc2"], history[1].corpora);
+
+    // Only the most recent entries are kept
+    store
+        .add_history_entry("user1", &entry("norm", &["pcc2"], None), 2)
+        .await
+        .unwrap();
+    let history = store.history("user1").await.unwrap();
+    assert_eq!(vec![("norm", None), ("tok", Some(11))], queries(&history));
+
+    let history = store.history("user2").await.unwrap();
+    assert_eq!(vec![("lemma", Some(1))], queries(&history));
+}
+
+#[test(tokio::test)]
+async fn user_store_history_context() {
+    let store = UserStore::new(None).unwrap();
+    store.migrate().await.unwrap();
+
+    let config = CSVConfig {
+        left_context: 3,
+        right_context: 7,
+        span_segmentation: Some("dipl".to_string()),
+        ..Default::default()
+    };
+    let corpora = BTreeSet::from(["RIDGES".to_string()]);
+    let entry = HistoryEntry::new("tok", QueryLanguage::AQL, &config, &corpora, None);
+    store.add_history_entry("user1", &entry, 2).await.unwrap();
+
+    let history = store.history("user1").await.unwrap();
+    assert_eq!(1, history.len());
+    assert_eq!(3, history[0].left_context);
+    assert_eq!(7, history[0].right_context);
+    assert_eq!(Some("dipl".to_string()), history[0].segmentation);
+    assert_eq!(entry.executed, history[0].executed);
+}
+
+#[test(tokio::test)]
+async fn user_store_history_match_count() {
+    let store = UserStore::new(None).unwrap();
+    store.migrate().await.unwrap();
+
+    let exported = entry("tok", &["pcc2"], None);
+    store
+        .add_history_entry("user1", &exported, 5)
+        .await
+        .unwrap();
+    store
+        .add_history_entry("user1", &entry("pos", &["pcc2"], Some(3)), 5)
+        .await
+        .unwrap();
+
+    // The position of the entry is kept
+    store
+        .set_history_match_count("user1", &exported, 10)
+        .await
+        .unwrap();
+    let history = store.history("user1").await.unwrap();
+    assert_eq!(vec![("pos", Some(3)), ("tok", Some(10))], queries(&history));
+
+    // Entries that have been replaced in the meantime are not changed
+    store
+        .add_history_entry("user1", &entry("tok", &["pcc2"], Some(11)), 5)
+        .await
+        .unwrap();
+    store
+        .set_history_match_count("user1", &exported, 12)
+        .await
+        .unwrap();
+    let history = store.history("user1").await.unwrap();
+    assert_eq!(vec![("tok", Some(11)), ("pos", Some(3))], queries(&history));
+}
@@ -1,10 +1,17 @@
 mod auth;
+pub mod citation;
 pub mod client;
+pub mod collocation;
+pub mod concordance;
 pub mod config;
 pub mod converter;
 pub(crate) mod errors;
+pub mod history;
 pub mod state;
+pub mod user_store;
 mod views;
+pub mod visualizer;
+pub mod wordlist;
 
 use axum::{
     body::{self, Empty, Full},
@@ -78,15 +85,26 @@ async fn app_with_state<S: SessionStore>(
     session_store: S,
     cleanup_interval: Duration,
 ) -> Result<Router> {
+    global_state.user_store.migrate().await?;
+
     let routes = Router::new()
         .route("/", get(|| async { Redirect::temporary("corpora") }))
         .route("/static/*path", get(static_file))
         .nest("/corpora", views::corpora::create_routes()?)
         .nest("/export", views::export::create_routes()?)
         .nest("/about", views::about::create_routes()?)
+        .nest("/collocations", views::collocations::create_routes()?)
+        .nest("/comparison", views::comparison::create_routes()?)
+        .nest("/distribution", views::distribution::create_routes()?)
         .nest("/oauth", views::oauth::create_routes()?)
+        .nest("/permalink", views::permalink::create_routes()?)
+        .nest("/query", views::query::create_routes()?)
+        .nest("/saved", views::saved::create_routes()?)
+        .nest("/search", views::search::create_routes()?)
+        .nest("/wordlist", views::wordlist::create_routes()?)
         .with_state(global_state.clone());
 
+    let cleanup_store = session_store.clone();
     let session_service = ServiceBuilder::new()
         .layer(HandleErrorLayer::new(|_: BoxError| async {
             StatusCode::BAD_REQUEST
@@ -97,7 +115,7 @@ async fn app_with_state<S: SessionStore>(
     tokio::task::spawn(async move {
         loop {
             tokio::time::sleep(cleanup_interval).await;
-            global_state.cleanup().await;
+            global_state.cleanup(&cleanup_store).await;
         }
     });
 
@@ -1,20 +1,44 @@
 use crate::auth::LoginInfo;
+use crate::concordance::SortedMatches;
+use crate::history::HistoryEntry;
+use crate::user_store::UserStore;
+use crate::views::corpora::AnnotationInventory;
 use crate::{config::CliConfig, errors::AppError, Result, TEMPLATES_DIR};
 use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use dashmap::DashMap;
+use graphannis::graph::Annotation;
 use minijinja::Value;
 use oauth2::{basic::BasicClient, PkceCodeVerifier};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
 use serde::{Deserialize, Serialize};
 use std::{collections::BTreeSet, sync::Arc};
 use tempfile::NamedTempFile;
 use time::OffsetDateTime;
 use tokio::{sync::mpsc::Receiver, task::JoinHandle};
+use tower_sessions::{session::SessionId, SessionStore};
 use url::Url;
 
+/// Characters that need to be encoded when a value is used as a single segment
+/// of a URL path.
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'<')
+    .add(b'>')
+    .add(b'`')
+    .add(b'?')
+    .add(b'{')
+    .add(b'}')
+    .add(b'%')
+    .add(b'/');
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Session {
     selected_corpora: BTreeSet<String>,
+    /// A second set of corpora the selected corpora can be compared with.
+    comparison_corpora: BTreeSet<String>,
     #[serde(skip)]
     session: tower_sessions::Session,
     session_id: String,
@@ -22,18 +46,39 @@ pub struct Session {
 
 impl Session {
     pub const SELECTED_CORPORA_KEY: &'static str = "selected_corpora";
+    pub const COMPARISON_CORPORA_KEY: &'static str = "comparison_corpora";
+    pub const QUERY_HISTORY_KEY: &'static str = "query_history";
 
     fn update_session(
         session: &tower_sessions::Session,
         selected_corpora: &BTreeSet<String>,
+        comparison_corpora: &BTreeSet<String>,
     ) -> Result<()> {
         session.insert(Self::SELECTED_CORPORA_KEY, selected_corpora.clone())?;
+        session.insert(Self::COMPARISON_CORPORA_KEY, comparison_corpora.clone())?;
         Ok(())
     }
 
-    pub fn set_selected_corpora(&mut self, selected_corpora: BTreeSet<String>) -> Result<()> {
+    /// Set the selected corpora of the session. If a user is logged in, the
+    /// selection is also stored for the user, so it can be restored in
+    /// another session.
+    pub async fn set_selected_corpora(
+        &mut self,
+        selected_corpora: BTreeSet<String>,
+        state: &GlobalAppState,
+    ) -> Result<()> {
         self.selected_corpora = selected_corpora;
-        Self::update_session(&self.session, &self.selected_corpora)?;
+        Self::update_session(
+            &self.session,
+            &self.selected_corpora,
+            &self.comparison_corpora,
+        )?;
+        if let Some(user_id) = state.user_id(self)? {
+            state
+                .user_store
+                .set_selected_corpora(&user_id, &self.selected_corpora)
+                .await?;
+        }
         Ok(())
     }
 
@@ -41,6 +86,31 @@ impl Session {
         &self.selected_corpora
     }
 
+    pub fn set_comparison_corpora(&mut self, comparison_corpora: BTreeSet<String>) -> Result<()> {
+        self.comparison_corpora = comparison_corpora;
+        Self::update_session(
+            &self.session,
+            &self.selected_corpora,
+            &self.comparison_corpora,
+        )?;
+        Ok(())
+    }
+
+    pub fn comparison_corpora(&self) -> &BTreeSet<String> {
+        &self.comparison_corpora
+    }
+
+    /// The queries executed in this session, most recent first.
+    pub fn query_history(&self) -> Result<Vec<HistoryEntry>> {
+        let history = self.session.get(Self::QUERY_HISTORY_KEY)?;
+        Ok(history.unwrap_or_default())
+    }
+
+    pub fn set_query_history(&self, history: Vec<HistoryEntry>) -> Result<()> {
+        self.session.insert(Self::QUERY_HISTORY_KEY, history)?;
+        Ok(())
+    }
+
     pub fn id(&self) -> &str {
         &self.session_id
     }
@@ -65,13 +135,17 @@ where
         let selected_corpora: BTreeSet<String> = session
             .get(Session::SELECTED_CORPORA_KEY)?
             .unwrap_or_default();
+        let comparison_corpora: BTreeSet<String> = session
+            .get(Session::COMPARISON_CORPORA_KEY)?
+            .unwrap_or_default();
 
-        Self::update_session(&session, &selected_corpora)?;
+        Self::update_session(&session, &selected_corpora, &comparison_corpora)?;
 
         Ok(Self {
             session_id: session.id().to_string(),
             session,
             selected_corpora,
+            comparison_corpora,
         })
     }
 }
@@ -101,8 +175,27 @@ impl ExportJob {
         }
         self.progress
     }
+
+    pub fn state(&mut self) -> JobState {
+        if self.handle.is_finished() {
+            JobState::Finished
+        } else {
+            JobState::Running(self.get_progress())
+        }
+    }
 }
 
+/// The state of a background job, as shown in the templates.
+#[derive(Clone, Debug, Serialize)]
+pub enum JobState {
+    Idle,
+    Running(f32),
+    Finished,
+}
+
+/// User ID (if logged in), selected corpora and whether values are included.
+pub type NodeAnnotationsKey = (Option<String>, BTreeSet<String>, bool);
+
 #[derive(Clone)]
 pub enum SessionArg {
     Session(Session),
@@ -118,13 +211,45 @@ impl SessionArg {
     }
 }
 
+/// Whether the session is still stored and not expired. Errors of the store
+/// are not treated as a missing session.
+async fn session_exists<S: SessionStore>(session_store: &S, session_id: &str) -> bool {
+    let Ok(session_id) = SessionId::try_from(session_id) else {
+        return false;
+    };
+    match session_store.load(&session_id).await {
+        Ok(Some(session)) => session.active(),
+        Ok(None) => false,
+        Err(_) => true,
+    }
+}
+
 pub struct GlobalAppState {
     pub service_url: Url,
+    /// The URL prefix of this web application, used for links outside of
+    /// the templates, e.g. in exported files.
+    pub frontend_prefix: String,
     pub templates: minijinja::Environment<'static>,
     pub oauth2_client: Option<BasicClient>,
     pub background_jobs: DashMap<String, ExportJob>,
     pub auth_requests: DashMap<String, PkceCodeVerifier>,
     pub login_info: Arc<DashMap<String, LoginInfo>>,
+    /// The last sorted matches of each session.
+    pub sorted_matches: DashMap<String, SortedMatches>,
+    /// Running or finished word list jobs, which write a CSV file.
+    pub word_list_jobs: DashMap<String, ExportJob>,
+    /// The CSV file of the last finished word list of each session.
+    pub word_lists: DashMap<String, NamedTempFile>,
+    /// Data of logged-in users that is kept across sessions.
+    pub user_store: UserStore,
+    /// Node annotations of a set of corpora for the query suggestions, with
+    /// or without their most frequent values. The cache is separated by the
+    /// ID of the logged-in user, so only users with access to the corpora
+    /// can get their annotations.
+    pub node_annotations: DashMap<NodeAnnotationsKey, Vec<Annotation>>,
+    /// Annotations and components shown on the information page of a corpus,
+    /// cached by the ID of the logged-in user and the corpus name.
+    pub annotation_inventories: DashMap<(Option<String>, String), AnnotationInventory>,
     default_client: reqwest::Client,
 }
 
@@ -150,6 +275,22 @@ impl GlobalAppState {
         let login_info: DashMap<String, LoginInfo> = DashMap::new();
         let login_info = Arc::new(login_info);
 
+        // Add a filter to use values like corpus names as a single segment of a URL path
+        templates.add_filter("path_segment", |value: String| -> String {
+            utf8_percent_encode(&value, PATH_SEGMENT).to_string()
+        });
+
+        // Add a filter to show timestamps in RFC 3339 format as UTC date and time
+        templates.add_filter("datetime", |value: String| -> String {
+            match DateTime::parse_from_rfc3339(&value) {
+                Ok(timestamp) => timestamp
+                    .with_timezone(&Utc)
+                    .format("%Y-%m-%d %H:%M UTC")
+                    .to_string(),
+                Err(_) => value,
+            }
+        });
+
         // Add a function for the template that allows to easily extract the username
         let login_info_for_template = login_info.clone();
         templates.add_function("username", move |session: Value| -> Value {
@@ -171,10 +312,17 @@ impl GlobalAppState {
         let default_client = reqwest::ClientBuilder::new().build()?;
         let result = Self {
             service_url,
+            frontend_prefix: config.frontend_prefix.to_string(),
             background_jobs: DashMap::new(),
             templates,
             auth_requests: DashMap::new(),
             login_info,
+            sorted_matches: DashMap::new(),
+            word_list_jobs: DashMap::new(),
+            word_lists: DashMap::new(),
+            user_store: UserStore::new(config.session_file.as_deref())?,
+            node_annotations: DashMap::new(),
+            annotation_inventories: DashMap::new(),
             oauth2_client,
             default_client,
         };
@@ -212,8 +360,17 @@ impl GlobalAppState {
         }
     }
 
+    /// The ID of the user that is logged in with this session, if any.
+    pub fn user_id(&self, session: &Session) -> Result<Option<String>> {
+        if let Some(login_info) = self.login_info.get(session.id()) {
+            login_info.user_id()
+        } else {
+            Ok(None)
+        }
+    }
+
     /// Cleans up ressources coupled to sessions that are expired or non-existing.
-    pub async fn cleanup(&self) {
+    pub async fn cleanup<S: SessionStore>(&self, session_store: &S) {
         self.login_info.retain(|_session_id, login_info| {
             if let Some(expiry) = login_info.expires_unix() {
                 Utc::now().timestamp() < expiry
@@ -221,6 +378,27 @@ impl GlobalAppState {
                 true
             }
         });
+        let session_ids: BTreeSet<String> = self
+            .sorted_matches
+            .iter()
+            .map(|entry| entry.key().clone())
+            .chain(self.word_list_jobs.iter().map(|entry| entry.key().clone()))
+            .chain(self.word_lists.iter().map(|entry| entry.key().clone()))
+            .collect();
+        for session_id in session_ids {
+            if !session_exists(session_store, &session_id).await {
+                self.sorted_matches.remove(&session_id);
+                if let Some((_, job)) = self.word_list_jobs.remove(&session_id) {
+                    job.handle.abort();
+                }
+                // Dropping the file also deletes it from the disk
+                self.word_lists.remove(&session_id);
+            }
+        }
+        // Annotations can change when corpora are updated, so only keep them
+        // until the next cleanup
+        self.node_annotations.clear();
+        self.annotation_inventories.clear();
     }
 }
 
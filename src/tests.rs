@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeSet,
     fmt::Debug,
     net::{SocketAddr, TcpListener},
 };
@@ -7,6 +8,7 @@ use axum::{
     body::{Body, HttpBody},
     http::{Request, Response, StatusCode},
 };
+use base64::Engine;
 use chrono::Duration;
 use cookie::Cookie;
 use fantoccini::{wd::Capabilities, ClientBuilder};
@@ -16,8 +18,9 @@ use tempfile::TempDir;
 use test_log::test;
 use tokio::task::JoinHandle;
 use tower::ServiceExt;
+use tower_sessions::{sqlx::SqlitePool, Session, SessionRecord, SessionStore, SqliteStore};
 
-use crate::config::CliConfig;
+use crate::{auth::LoginInfo, config::CliConfig};
 
 #[derive(Debug)]
 pub struct TestEnvironment {
@@ -100,6 +103,60 @@ pub async fn start_end2end_servers() -> TestEnvironment {
     }
 }
 
+/// Creates a new session with the given corpora selected and stores it in an
+/// in-memory session store. Returns the cookie header value for the session
+/// and the session store, which can be used with [`crate::app_with_state`].
+pub async fn create_session_with_corpora(selected_corpora: &[&str]) -> (String, SqliteStore) {
+    let session = Session::new(None);
+    let selected_corpora: BTreeSet<String> =
+        selected_corpora.iter().map(|c| c.to_string()).collect();
+    session
+        .insert(
+            crate::state::Session::SELECTED_CORPORA_KEY,
+            selected_corpora,
+        )
+        .unwrap();
+
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    let session_store = SqliteStore::new(pool);
+    session_store.migrate().await.unwrap();
+    session_store
+        .save(&SessionRecord::from(&session))
+        .await
+        .unwrap();
+
+    let session_cookie = Cookie::build(("tower.sid", session.id().to_string()));
+    (session_cookie.to_string(), session_store)
+}
+
+/// Adds another empty session to the store and returns its ID and the cookie
+/// header value for it.
+pub async fn add_session(session_store: &SqliteStore) -> (String, String) {
+    let session = Session::new(None);
+    session_store
+        .save(&SessionRecord::from(&session))
+        .await
+        .unwrap();
+    let session_cookie = Cookie::build(("tower.sid", session.id().to_string()));
+    (session.id().to_string(), session_cookie.to_string())
+}
+
+/// Creates the login information for a user with an unsigned token that has
+/// the user ID as subject.
+pub fn login_info(user_id: &str) -> LoginInfo {
+    let claims = json!({ "sub": user_id }).to_string();
+    let token = format!(
+        "eyJhbGciOiJub25lIn0.{}.",
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(claims)
+    );
+    let token_response = oauth2::StandardTokenResponse::new(
+        oauth2::AccessToken::new(token),
+        oauth2::basic::BasicTokenType::Bearer,
+        oauth2::EmptyExtraTokenFields {},
+    );
+    LoginInfo::from_token(token_response, None).unwrap()
+}
+
 pub async fn get_body<T>(response: Response<T>) -> String
 where
     T: HttpBody,
@@ -0,0 +1,345 @@
+//! Persistent storage of data that belongs to a logged-in user and not only
+//! to a single session. The data is stored in the same SQLite database as
+//! the sessions or, if no session file is configured, in memory.
+
+use std::{collections::BTreeSet, path::Path, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use graphannis::corpusstorage::QueryLanguage;
+use serde::Serialize;
+use tower_sessions::sqlx::{
+    self,
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions, SqliteRow},
+    Row, SqlitePool,
+};
+
+use crate::{history::HistoryEntry, Result};
+
+/// A named query of a user, which can be shared with other users by its
+/// link.
+#[derive(Serialize, Debug, Clone)]
+pub struct SavedQuery {
+    /// Random identifier that is used in the link of the saved query.
+    pub id: String,
+    /// The user that owns the query and is allowed to change it.
+    pub user_id: String,
+    /// Folder to organize the saved queries in, empty if not in a folder.
+    pub folder: String,
+    pub name: String,
+    pub description: String,
+    pub query: String,
+    pub query_language: QueryLanguage,
+    pub corpora: Vec<String>,
+    /// Context and segmentation the matches are shown with.
+    pub left_context: usize,
+    pub right_context: usize,
+    pub segmentation: Option<String>,
+}
+
+impl SavedQuery {
+    fn from_row(row: &SqliteRow) -> Result<Self> {
+        let query_language: String = row.try_get("query_language")?;
+        let corpora: String = row.try_get("corpora")?;
+        let left_context: i64 = row.try_get("left_context")?;
+        let right_context: i64 = row.try_get("right_context")?;
+        Ok(Self {
+            id: row.try_get("id")?,
+            user_id: row.try_get("user_id")?,
+            folder: row.try_get("folder")?,
+            name: row.try_get("name")?,
+            description: row.try_get("description")?,
+            query: row.try_get("query")?,
+            query_language: serde_json::from_str(&query_language)?,
+            corpora: serde_json::from_str(&corpora)?,
+            left_context: left_context as usize,
+            right_context: right_context as usize,
+            segmentation: row.try_get("segmentation")?,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct UserStore {
+    pool: SqlitePool,
+}
+
+impl UserStore {
+    /// Create a store for the given database file. The connection is only
+    /// established when the store is used first.
+    pub fn new(database_file: Option<&Path>) -> Result<Self> {
+        let pool = if let Some(database_file) = database_file {
+            let options = SqliteConnectOptions::new()
+                .filename(database_file)
+                .create_if_missing(true);
+            SqlitePoolOptions::new().connect_lazy_with(options)
+        } else {
+            // An in-memory database only exists as long as its connection, so
+            // never close it
+            let options = SqliteConnectOptions::from_str("sqlite::memory:")?;
+            SqlitePoolOptions::new()
+                .max_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None)
+                .connect_lazy_with(options)
+        };
+        Ok(Self { pool })
+    }
+
+    /// Create the tables of the store if they do not exist yet.
+    pub async fn migrate(&self) -> Result<()> {
+        sqlx::query(
+            r#"
+            create table if not exists query_history
+            (
+                id integer primary key autoincrement,
+                user_id text not null,
+                query text not null,
+                query_language text not null,
+                corpora text not null,
+                left_context integer not null,
+                right_context integer not null,
+                segmentation text null,
+                executed text not null,
+                match_count integer null
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+        sqlx::query(
+            r#"
+            create table if not exists saved_queries
+            (
+                id text primary key not null,
+                user_id text not null,
+                folder text not null,
+                name text not null,
+                description text not null,
+                query text not null,
+                query_language text not null,
+                corpora text not null,
+                left_context integer not null,
+                right_context integer not null,
+                segmentation text null
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+        sqlx::query(
+            r#"
+            create table if not exists selected_corpora
+            (
+                user_id text primary key not null,
+                corpora text not null
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// The corpora the user selected last, empty if the user never selected
+    /// any corpus.
+    pub async fn selected_corpora(&self, user_id: &str) -> Result<BTreeSet<String>> {
+        let row = sqlx::query("select corpora from selected_corpora where user_id = ?")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        if let Some(row) = row {
+            let corpora: String = row.try_get("corpora")?;
+            Ok(serde_json::from_str(&corpora)?)
+        } else {
+            Ok(BTreeSet::new())
+        }
+    }
+
+    pub async fn set_selected_corpora(
+        &self,
+        user_id: &str,
+        corpora: &BTreeSet<String>,
+    ) -> Result<()> {
+        sqlx::query("insert or replace into selected_corpora (user_id, corpora) values (?, ?)")
+            .bind(user_id)
+            .bind(serde_json::to_string(corpora)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Add the entry to the query history of the user. Older entries for the
+    /// same query and corpora are replaced and only the `max_entries` most
+    /// recent entries are kept.
+    pub async fn add_history_entry(
+        &self,
+        user_id: &str,
+        entry: &HistoryEntry,
+        max_entries: usize,
+    ) -> Result<()> {
+        let query_language = serde_json::to_string(&entry.query_language)?;
+        let corpora = serde_json::to_string(&entry.corpora)?;
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+            delete from query_history
+            where user_id = ? and query = ? and query_language = ? and corpora = ?
+            "#,
+        )
+        .bind(user_id)
+        .bind(&entry.query)
+        .bind(&query_language)
+        .bind(&corpora)
+        .execute(&mut *transaction)
+        .await?;
+        sqlx::query(
+            r#"
+            insert into query_history
+            (user_id, query, query_language, corpora, left_context, right_context,
+            segmentation, executed, match_count)
+            values (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(user_id)
+        .bind(&entry.query)
+        .bind(&query_language)
+        .bind(&corpora)
+        .bind(entry.left_context as i64)
+        .bind(entry.right_context as i64)
+        .bind(&entry.segmentation)
+        .bind(entry.executed.to_rfc3339())
+        .bind(entry.match_count.map(|c| c as i64))
+        .execute(&mut *transaction)
+        .await?;
+        sqlx::query(
+            r#"
+            delete from query_history
+            where user_id = ? and id not in
+            (select id from query_history where user_id = ? order by id desc limit ?)
+            "#,
+        )
+        .bind(user_id)
+        .bind(user_id)
+        .bind(max_entries as i64)
+        .execute(&mut *transaction)
+        .await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    /// Set the number of matches of an entry in the query history of the
+    /// user, without changing its position. Nothing is changed if the entry
+    /// has already been replaced by a newer one.
+    pub async fn set_history_match_count(
+        &self,
+        user_id: &str,
+        entry: &HistoryEntry,
+        match_count: u64,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            update query_history set match_count = ?
+            where user_id = ? and query = ? and query_language = ? and corpora = ?
+            and executed = ?
+            "#,
+        )
+        .bind(match_count as i64)
+        .bind(user_id)
+        .bind(&entry.query)
+        .bind(serde_json::to_string(&entry.query_language)?)
+        .bind(serde_json::to_string(&entry.corpora)?)
+        .bind(entry.executed.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// The query history of the user, most recent entry first.
+    pub async fn history(&self, user_id: &str) -> Result<Vec<HistoryEntry>> {
+        let rows = sqlx::query(
+            r#"
+            select query, query_language, corpora, left_context, right_context,
+            segmentation, executed, match_count
+            from query_history where user_id = ? order by id desc
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut result = Vec::with_capacity(rows.len());
+        for row in rows {
+            let query_language: String = row.try_get("query_language")?;
+            let corpora: String = row.try_get("corpora")?;
+            let left_context: i64 = row.try_get("left_context")?;
+            let right_context: i64 = row.try_get("right_context")?;
+            let executed: String = row.try_get("executed")?;
+            let match_count: Option<i64> = row.try_get("match_count")?;
+            result.push(HistoryEntry {
+                query: row.try_get("query")?,
+                query_language: serde_json::from_str(&query_language)?,
+                corpora: serde_json::from_str(&corpora)?,
+                left_context: left_context as usize,
+                right_context: right_context as usize,
+                segmentation: row.try_get("segmentation")?,
+                executed: DateTime::parse_from_rfc3339(&executed)?.with_timezone(&Utc),
+                match_count: match_count.map(|c| c as u64),
+            });
+        }
+        Ok(result)
+    }
+
+    /// All saved queries of the user, sorted by their folder and name.
+    pub async fn saved_queries(&self, user_id: &str) -> Result<Vec<SavedQuery>> {
+        let rows =
+            sqlx::query("select * from saved_queries where user_id = ? order by folder, name, id")
+                .bind(user_id)
+                .fetch_all(&self.pool)
+                .await?;
+        rows.iter().map(SavedQuery::from_row).collect()
+    }
+
+    /// Get a saved query of any user by its ID.
+    pub async fn saved_query(&self, id: &str) -> Result<Option<SavedQuery>> {
+        let row = sqlx::query("select * from saved_queries where id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(SavedQuery::from_row).transpose()
+    }
+
+    /// Insert the saved query or, if a query with the same ID exists, replace
+    /// it.
+    pub async fn save_query(&self, saved_query: &SavedQuery) -> Result<()> {
+        sqlx::query(
+            r#"
+            insert or replace into saved_queries
+            (id, user_id, folder, name, description, query, query_language, corpora,
+            left_context, right_context, segmentation)
+            values (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&saved_query.id)
+        .bind(&saved_query.user_id)
+        .bind(&saved_query.folder)
+        .bind(&saved_query.name)
+        .bind(&saved_query.description)
+        .bind(&saved_query.query)
+        .bind(serde_json::to_string(&saved_query.query_language)?)
+        .bind(serde_json::to_string(&saved_query.corpora)?)
+        .bind(saved_query.left_context as i64)
+        .bind(saved_query.right_context as i64)
+        .bind(&saved_query.segmentation)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_saved_query(&self, id: &str) -> Result<()> {
+        sqlx::query("delete from saved_queries where id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
@@ -1,4 +1,12 @@
 pub mod about;
+pub mod collocations;
+pub mod comparison;
 pub mod corpora;
+pub mod distribution;
 pub mod export;
 pub mod oauth;
+pub mod permalink;
+pub mod query;
+pub mod saved;
+pub mod search;
+pub mod wordlist;
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    response::{Html, IntoResponse},
+    routing::get,
+    Router,
+};
+use graphannis::corpusstorage::{QueryLanguage, ResultOrder};
+use minijinja::context;
+use serde::Deserialize;
+
+use crate::{
+    client::search::FindQuery,
+    collocation::{self, CollocationConfig},
+    state::{GlobalAppState, Session, SessionArg},
+    Result,
+};
+
+/// Number of collocates shown in the result table.
+const MAX_COLLOCATES: usize = 100;
+
+pub fn create_routes() -> Result<Router<Arc<GlobalAppState>>> {
+    let result = Router::new().route("/", get(show_page));
+    Ok(result)
+}
+
+#[derive(Deserialize, Debug)]
+struct FormParams {
+    query: Option<String>,
+    #[serde(default)]
+    query_language: QueryLanguage,
+    #[serde(flatten)]
+    config: CollocationConfig,
+}
+
+/// Show the form and, if a query is given, the collocates of its matches in
+/// the selected corpora.
+async fn show_page(
+    session: Session,
+    Query(params): Query<FormParams>,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    let query = params.query.clone().unwrap_or_default();
+    let result = if query.trim().is_empty() || session.selected_corpora().is_empty() {
+        None
+    } else {
+        let find_query = FindQuery {
+            query: query.clone(),
+            corpora: session.selected_corpora().iter().cloned().collect(),
+            query_language: params.query_language,
+            limit: None,
+            offset: 0,
+            order: ResultOrder::Normal,
+        };
+        let mut result = collocation::analyze(
+            &SessionArg::Session(session.clone()),
+            &find_query,
+            &params.config,
+            &app_state,
+        )
+        .await?;
+        result.collocates.truncate(MAX_COLLOCATES);
+        Some(result)
+    };
+
+    let html = app_state
+        .templates
+        .get_template("collocations.html")?
+        .render(context! {
+            session => session,
+            query,
+            query_language => params.query_language,
+            config => params.config,
+            result,
+        })?;
+    Ok(Html(html))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use mockito::{Matcher, Server};
+use scraper::Selector;
+use test_log::test;
+use tower::ServiceExt;
+
+use crate::{
+    collocation::MAX_ANALYZED_MATCHES,
+    config::CliConfig,
+    state::GlobalAppState,
+    tests::{create_session_with_corpora, get_html},
+};
+
+#[test(tokio::test)]
+async fn collocates_of_matches() {
+    let mut service_mock = Server::new_with_port(0);
+    service_mock
+        .mock("POST", "/search/find")
+        .match_body(Matcher::PartialJsonString(format!(
+            r#"{{"limit": {}, "query_language": "AQLQuirksV3"}}"#,
+            MAX_ANALYZED_MATCHES + 1
+        )))
+        .with_header("content-type", "text/plain")
+        .with_body("corefcorpus/doc1#t2\ncorefcorpus/doc1#t3\ncorefcorpus/doc1#t6\n")
+        .create();
+    service_mock
+        .mock("POST", "/corpora/corefcorpus/subgraph")
+        .with_body_from_file("tests/coreference-document.graphml")
+        .create();
+    service_mock
+        .mock("POST", "/search/frequency")
+        .with_header("content-type", "application/json")
+        .with_body(
+            r#"[
+                {"values": ["."], "count": 20},
+                {"values": ["Peter"], "count": 10},
+                {"values": ["Er"], "count": 2}
+            ]"#,
+        )
+        .create();
+    service_mock
+        .mock("POST", "/search/count")
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"match_count": 100, "document_count": 1}"#)
+        .create();
+
+    let config = CliConfig {
+        service_url: service_mock.url(),
+        ..Default::default()
+    };
+    let (session_cookie, session_store) = create_session_with_corpora(&["corefcorpus"]).await;
+    let app_state = Arc::new(GlobalAppState::new(&config).unwrap());
+    let app = crate::app_with_state(app_state, session_store, chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/collocations?query=tok&query_language=AQLQuirksV3&window=2&annotation=&min_frequency=2&measure=t_score")
+                .header("Cookie", session_cookie)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = get_html(response).await;
+
+    // Only the values that occur at least twice in the two token left and
+    // right of the matches are shown, ordered by their t-score
+    let collocates: Vec<_> = html
+        .select(&Selector::parse("td.collocate").unwrap())
+        .map(|td| td.inner_html())
+        .collect();
+    assert_eq!(vec!["Er", ".", "Peter"], collocates);
+    let summary = html
+        .select(&Selector::parse("#collocation-summary").unwrap())
+        .next()
+        .unwrap()
+        .inner_html();
+    assert!(summary.contains("3 matches with 11 token"));
+    assert!(html
+        .select(&Selector::parse(".notification.is-warning").unwrap())
+        .next()
+        .is_none());
+    // The query language is kept in the form
+    let query_language = html
+        .select(&Selector::parse("select[name=query_language] option[selected]").unwrap())
+        .next()
+        .and_then(|o| o.value().attr("value"))
+        .map(str::to_string);
+    assert_eq!(Some("AQLQuirksV3".to_string()), query_language);
+}
@@ -0,0 +1,305 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+use axum::{
+    extract::{Query, State},
+    response::{Html, IntoResponse},
+    routing::{get, post},
+    Form, Router,
+};
+use graphannis::corpusstorage::{FrequencyDefEntry, QueryLanguage};
+use graphannis_core::{graph::ANNIS_NS, util::split_qname};
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, NoneAsEmptyString};
+
+use crate::{
+    client::{
+        corpora,
+        search::{self, CountQuery, FrequencyQuery},
+    },
+    collocation::log_likelihood,
+    state::{GlobalAppState, Session, SessionArg},
+    Result,
+};
+
+/// Frequencies are normalized to the number of occurrences per this many
+/// token.
+const NORMALIZATION_BASE: f64 = 1000.0;
+
+/// Number of rows shown in the frequency table.
+const MAX_FREQUENCY_ROWS: usize = 100;
+
+/// Critical values of the log-likelihood (with one degree of freedom) for
+/// the significance levels p < 0.05, p < 0.01 and p < 0.001.
+const CRITICAL_VALUES: [f64; 3] = [3.84, 6.63, 10.83];
+
+pub fn create_routes() -> Result<Router<Arc<GlobalAppState>>> {
+    let result = Router::new()
+        .route("/", get(show_page))
+        .route("/corpora", post(update_corpora));
+    Ok(result)
+}
+
+#[serde_as]
+#[derive(Deserialize, Debug)]
+struct FormParams {
+    query: Option<String>,
+    /// Qualified name of the annotation of the first query node whose values
+    /// are compared, the token value is used if not given.
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    annotation: Option<String>,
+    #[serde(default)]
+    query_language: QueryLanguage,
+}
+
+/// Counts of the query in one of the two corpus sets.
+#[derive(Serialize, Debug, Default)]
+struct CorpusSetResult {
+    match_count: u64,
+    token_count: u64,
+    /// Matches per [`NORMALIZATION_BASE`] token.
+    relative_frequency: f64,
+}
+
+/// The frequency of an annotation value in both corpus sets.
+#[derive(Serialize, Debug)]
+struct FrequencyComparison {
+    value: String,
+    counts: [usize; 2],
+    relative_frequencies: [f64; 2],
+    log_likelihood: f64,
+    /// Number of the critical values the log-likelihood exceeds, from 0 (not
+    /// significant) to 3 (p < 0.001).
+    significance: usize,
+}
+
+#[derive(Serialize, Debug)]
+struct ComparisonResult {
+    sets: [CorpusSetResult; 2],
+    log_likelihood: f64,
+    significance: usize,
+    frequencies: Vec<FrequencyComparison>,
+}
+
+fn relative_frequency(count: f64, token_count: f64) -> f64 {
+    if token_count > 0.0 {
+        count * NORMALIZATION_BASE / token_count
+    } else {
+        0.0
+    }
+}
+
+fn significance(log_likelihood: f64) -> usize {
+    CRITICAL_VALUES
+        .iter()
+        .filter(|critical| log_likelihood >= **critical)
+        .count()
+}
+
+/// Compare the frequencies of observing something in the first or second of
+/// two corpus sets with the given sizes.
+fn compare(counts: [f64; 2], token_counts: [f64; 2]) -> f64 {
+    log_likelihood([
+        counts,
+        [
+            (token_counts[0] - counts[0]).max(0.0),
+            (token_counts[1] - counts[1]).max(0.0),
+        ],
+    ])
+}
+
+async fn count(
+    session: &SessionArg,
+    query: &str,
+    query_language: QueryLanguage,
+    corpora: &BTreeSet<String>,
+    state: &GlobalAppState,
+) -> Result<u64> {
+    let query = CountQuery {
+        query: query.to_string(),
+        corpora: corpora.iter().cloned().collect(),
+        query_language,
+    };
+    Ok(search::count(session, &query, state).await?.match_count)
+}
+
+/// Count the query and the values of the annotation of its first node in both
+/// corpus sets.
+async fn compare_corpus_sets(
+    session: &Session,
+    query: &str,
+    query_language: QueryLanguage,
+    annotation: Option<&str>,
+    state: &GlobalAppState,
+) -> Result<ComparisonResult> {
+    let session_arg = SessionArg::Session(session.clone());
+    let corpus_sets = [session.selected_corpora(), session.comparison_corpora()];
+
+    let (ns, name) = match annotation {
+        Some(annotation) => split_qname(annotation),
+        None => (Some(ANNIS_NS), "tok"),
+    };
+    let mut sets: [CorpusSetResult; 2] = Default::default();
+    let mut values: BTreeMap<String, [usize; 2]> = BTreeMap::new();
+    for (idx, corpora) in corpus_sets.into_iter().enumerate() {
+        let match_count = count(&session_arg, query, query_language, corpora, state).await?;
+        let token_count = count(&session_arg, "tok", query_language, corpora, state).await?;
+        sets[idx] = CorpusSetResult {
+            match_count,
+            token_count,
+            relative_frequency: relative_frequency(match_count as f64, token_count as f64),
+        };
+
+        let frequency_query = FrequencyQuery {
+            query: query.to_string(),
+            corpora: corpora.iter().cloned().collect(),
+            query_language,
+            definition: vec![FrequencyDefEntry {
+                ns: ns.map(str::to_string),
+                name: name.to_string(),
+                node_ref: "1".to_string(),
+            }],
+        };
+        for row in search::frequency(&session_arg, &frequency_query, state).await? {
+            if let Some(value) = row.values.into_iter().next() {
+                values.entry(value).or_default()[idx] += row.count;
+            }
+        }
+    }
+    let token_counts = [sets[0].token_count as f64, sets[1].token_count as f64];
+
+    let mut frequencies: Vec<FrequencyComparison> = values
+        .into_iter()
+        .map(|(value, counts)| {
+            let counts_f = [counts[0] as f64, counts[1] as f64];
+            let log_likelihood = compare(counts_f, token_counts);
+            FrequencyComparison {
+                value,
+                counts,
+                relative_frequencies: [
+                    relative_frequency(counts_f[0], token_counts[0]),
+                    relative_frequency(counts_f[1], token_counts[1]),
+                ],
+                log_likelihood,
+                significance: significance(log_likelihood),
+            }
+        })
+        .collect();
+    frequencies.sort_by(|a, b| {
+        b.log_likelihood
+            .total_cmp(&a.log_likelihood)
+            .then_with(|| a.value.cmp(&b.value))
+    });
+    frequencies.truncate(MAX_FREQUENCY_ROWS);
+
+    let log_likelihood = compare(
+        [sets[0].match_count as f64, sets[1].match_count as f64],
+        token_counts,
+    );
+    Ok(ComparisonResult {
+        sets,
+        log_likelihood,
+        significance: significance(log_likelihood),
+        frequencies,
+    })
+}
+
+async fn render_page(
+    session: &Session,
+    params: &FormParams,
+    state: &GlobalAppState,
+) -> Result<Html<String>> {
+    let query = params.query.clone().unwrap_or_default();
+    let result = if query.trim().is_empty()
+        || session.selected_corpora().is_empty()
+        || session.comparison_corpora().is_empty()
+    {
+        None
+    } else {
+        Some(
+            compare_corpus_sets(
+                session,
+                &query,
+                params.query_language,
+                params.annotation.as_deref(),
+                state,
+            )
+            .await?,
+        )
+    };
+
+    let corpora: Vec<String> = corpora::list(&SessionArg::Session(session.clone()), state)
+        .await?
+        .into_iter()
+        .filter(|c| !session.comparison_corpora().contains(c))
+        .collect();
+
+    let html = state
+        .templates
+        .get_template("comparison.html")?
+        .render(context! {
+            session => session,
+            corpora,
+            query,
+            annotation => params.annotation,
+            query_language => params.query_language,
+            result,
+        })?;
+    Ok(Html(html))
+}
+
+/// Show the two corpus sets and, if a query is given, compare its frequency
+/// in both.
+async fn show_page(
+    session: Session,
+    Query(params): Query<FormParams>,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    render_page(&session, &params, &app_state).await
+}
+
+#[derive(Deserialize, Debug)]
+struct CorporaParams {
+    #[serde(flatten)]
+    form: FormParams,
+    add_corpus: Option<String>,
+    remove_corpus: Option<String>,
+    /// Use the currently selected corpora as the second corpus set.
+    use_selection: Option<String>,
+    /// Exchange the selected corpora and the second corpus set.
+    swap: Option<String>,
+}
+
+async fn update_corpora(
+    mut session: Session,
+    State(app_state): State<Arc<GlobalAppState>>,
+    Form(payload): Form<CorporaParams>,
+) -> Result<impl IntoResponse> {
+    let mut comparison_corpora = session.comparison_corpora().clone();
+    if let Some(add_corpus) = payload.add_corpus {
+        comparison_corpora.insert(add_corpus);
+    }
+    if let Some(remove_corpus) = payload.remove_corpus {
+        comparison_corpora.remove(&remove_corpus);
+    }
+    if payload.use_selection == Some("true".to_string()) {
+        comparison_corpora = session.selected_corpora().clone();
+    }
+    if payload.swap == Some("true".to_string()) {
+        let selected_corpora = session.selected_corpora().clone();
+        session
+            .set_selected_corpora(comparison_corpora, &app_state)
+            .await?;
+        comparison_corpora = selected_corpora;
+    }
+    session.set_comparison_corpora(comparison_corpora)?;
+
+    render_page(&session, &payload.form, &app_state).await
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,126 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{header::CONTENT_TYPE, Request, StatusCode},
+};
+use mockito::{Matcher, Server};
+use scraper::{Html, Selector};
+use test_log::test;
+use tower::ServiceExt;
+
+use crate::{
+    config::CliConfig,
+    state::GlobalAppState,
+    tests::{create_session_with_corpora, get_html},
+};
+
+/// Mocks the count of a query in compatibility mode and the frequency of its
+/// "pos" values in a corpus with 1000 token.
+fn mock_corpus(service_mock: &mut Server, corpus: &str, adv: usize, nn: usize) {
+    service_mock
+        .mock("POST", "/search/count")
+        .match_body(Matcher::PartialJsonString(format!(
+            r#"{{"query": "pos", "corpora": ["{corpus}"], "query_language": "AQLQuirksV3"}}"#
+        )))
+        .with_header("content-type", "application/json")
+        .with_body(format!(
+            r#"{{"match_count": {}, "document_count": 1}}"#,
+            adv + nn
+        ))
+        .create();
+    service_mock
+        .mock("POST", "/search/count")
+        .match_body(Matcher::PartialJsonString(format!(
+            r#"{{"query": "tok", "corpora": ["{corpus}"], "query_language": "AQLQuirksV3"}}"#
+        )))
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"match_count": 1000, "document_count": 1}"#)
+        .create();
+    service_mock
+        .mock("POST", "/search/frequency")
+        .match_body(Matcher::PartialJsonString(format!(
+            r#"{{"query": "pos", "corpora": ["{corpus}"], "query_language": "AQLQuirksV3"}}"#
+        )))
+        .with_header("content-type", "application/json")
+        .with_body(format!(
+            r#"[{{"values": ["ADV"], "count": {adv}}}, {{"values": ["NN"], "count": {nn}}}]"#
+        ))
+        .create();
+}
+
+#[test(tokio::test)]
+async fn compare_two_corpus_sets() {
+    let mut service_mock = Server::new_with_port(0);
+    service_mock
+        .mock("GET", "/corpora")
+        .with_header("content-type", "application/json")
+        .with_body(r#"["learner", "pcc2"]"#)
+        .create();
+    mock_corpus(&mut service_mock, "pcc2", 20, 200);
+    mock_corpus(&mut service_mock, "learner", 60, 190);
+
+    let config = CliConfig {
+        service_url: service_mock.url(),
+        ..Default::default()
+    };
+    let (session_cookie, session_store) = create_session_with_corpora(&["pcc2"]).await;
+    let app_state = Arc::new(GlobalAppState::new(&config).unwrap());
+    let app = crate::app_with_state(app_state, session_store, chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+
+    // Add a corpus to the second set
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/comparison/corpora")
+                .header("Cookie", session_cookie.clone())
+                .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(Body::from("add_corpus=learner&query=&annotation="))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = get_html(response).await;
+    let second_set: Vec<_> = html
+        .select(&Selector::parse("#second-corpus-set .tag").unwrap())
+        .map(|t| t.text().collect::<String>().trim().to_string())
+        .collect();
+    assert_eq!(vec!["learner"], second_set);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/comparison?query=pos&query_language=AQLQuirksV3&annotation=pos")
+                .header("Cookie", session_cookie)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = get_html(response).await;
+
+    let texts = |html: &Html, selector: &str| -> Vec<String> {
+        html.select(&Selector::parse(selector).unwrap())
+            .map(|e| e.text().collect::<String>().split_whitespace().collect())
+            .collect()
+    };
+    assert_eq!(
+        vec!["AQLQuirksV3"],
+        html.select(&Selector::parse("select[name='query_language'] option[selected]").unwrap())
+            .filter_map(|o| o.value().attr("value"))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(vec!["220", "250"], texts(&html, "td.comparison-matches"));
+    // Only the difference for "ADV" is significant
+    assert_eq!(vec!["ADV", "NN"], texts(&html, "td.comparison-value"));
+    assert_eq!(
+        vec!["21.76***", "0.32"],
+        texts(&html, "td.comparison-significance")
+    );
+}
@@ -1,23 +1,55 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
 
 use axum::{
-    extract::State,
+    body::StreamBody,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap},
     response::Html,
     response::IntoResponse,
     routing::{get, post},
     Form, Router,
 };
+use graphannis::{
+    corpusstorage::QueryLanguage,
+    graph::{AnnoKey, Annotation, NodeID},
+    model::{AnnotationComponent, AnnotationComponentType},
+    AnnotationGraph,
+};
+use graphannis_core::{
+    annostorage::ValueSearch,
+    graph::{ANNIS_NS, NODE_NAME_KEY},
+    util::join_qname,
+};
 use minijinja::context;
 use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, NoneAsEmptyString};
 
 use crate::{
-    client::corpora,
+    client::{
+        corpora::{self, CorpusConfiguration},
+        search::{self, CountQuery},
+    },
+    converter,
     state::{GlobalAppState, Session, SessionArg},
+    visualizer::{arcs, discourse},
     Result,
 };
 
 pub fn create_routes() -> Result<Router<Arc<GlobalAppState>>> {
-    let result = Router::new().route("/", get(show)).route("/", post(update));
+    let result = Router::new()
+        .route("/", get(show))
+        .route("/", post(update))
+        .route("/:name", get(show_info))
+        .route("/:name/documents", get(show_documents))
+        .route("/:name/documents/:document", get(show_document_text))
+        .route(
+            "/:name/documents/:document/discourse",
+            get(show_document_discourse),
+        )
+        .route("/:name/files/:file", get(get_file));
     Ok(result)
 }
 
@@ -98,7 +130,9 @@ async fn update(
     }
 
     // Update the session
-    session.set_selected_corpora(selected_corpora)?;
+    session
+        .set_selected_corpora(selected_corpora, &app_state)
+        .await?;
 
     let corpora: Vec<Corpus> = filtered_corpora
         .into_iter()
@@ -120,5 +154,636 @@ async fn update(
     Ok(Html(html))
 }
 
+#[derive(Serialize, Debug, Clone)]
+struct AnnotationSummary {
+    name: String,
+    /// Number of annotated nodes or edges, if it could be counted.
+    count: Option<u64>,
+}
+
+/// The annotations and components of a corpus, as shown on its information
+/// page.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct AnnotationInventory {
+    node_annotations: Vec<AnnotationSummary>,
+    edge_annotations: BTreeMap<String, Vec<AnnotationSummary>>,
+    components_by_type: BTreeMap<String, Vec<String>>,
+}
+
+/// The distinct keys of the given annotations, ignoring the internal
+/// annotations in the "annis" namespace.
+fn annotation_keys(annotations: Vec<Annotation>) -> BTreeSet<AnnoKey> {
+    annotations
+        .into_iter()
+        .filter(|anno| anno.key.ns != ANNIS_NS)
+        .map(|anno| anno.key)
+        .collect()
+}
+
+/// An AQL query that matches all nodes with the annotation or, if a
+/// component is given, all edges of the component with the annotation.
+/// Without a namespace, the annotation name matches all namespaces.
+fn annotation_query(key: &AnnoKey, component: Option<&AnnotationComponent>) -> String {
+    let qname = if key.ns.is_empty() {
+        key.name.to_string()
+    } else {
+        format!("{}:{}", key.ns, key.name)
+    };
+    if let Some(component) = component {
+        let operator = if component.get_type() == AnnotationComponentType::Dominance {
+            ">"
+        } else {
+            "->"
+        };
+        format!("node {operator}{}[{qname}=/.*/] node", component.name)
+    } else {
+        qname
+    }
+}
+
+/// Count the annotations with a count query, so the values do not have to be
+/// fetched. Annotation names that can not be expressed in AQL can not be
+/// counted.
+async fn summarize_annotations(
+    session: &SessionArg,
+    corpus: &str,
+    keys: BTreeSet<AnnoKey>,
+    component: Option<&AnnotationComponent>,
+    app_state: &GlobalAppState,
+) -> Vec<AnnotationSummary> {
+    let counts = keys.iter().map(|key| async move {
+        let count_query = CountQuery {
+            query: annotation_query(key, component),
+            corpora: vec![corpus.to_string()],
+            query_language: QueryLanguage::AQL,
+        };
+        match search::count(session, &count_query, app_state).await {
+            Ok(count) => Some(count.match_count),
+            Err(e) => {
+                tracing::warn!("Could not count annotation {}: {e}", count_query.query);
+                None
+            }
+        }
+    });
+    let counts = futures::future::join_all(counts).await;
+    keys.iter()
+        .zip(counts)
+        .map(|(key, count)| AnnotationSummary {
+            name: join_qname(&key.ns, &key.name),
+            count,
+        })
+        .collect()
+}
+
+/// Get the annotations and components of the corpus. The inventory is cached
+/// for each user and corpus.
+async fn annotation_inventory(
+    session: &Session,
+    corpus: &str,
+    app_state: &GlobalAppState,
+) -> Result<AnnotationInventory> {
+    let cache_key = (app_state.user_id(session)?, corpus.to_string());
+    if let Some(inventory) = app_state.annotation_inventories.get(&cache_key) {
+        return Ok(inventory.clone());
+    }
+    let session_arg = SessionArg::Session(session.clone());
+
+    let node_keys = annotation_keys(
+        corpora::node_annotations(&session_arg, corpus, false, false, app_state).await?,
+    );
+    let node_annotations =
+        summarize_annotations(&session_arg, corpus, node_keys, None, app_state).await;
+
+    let mut components_by_type: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut annotated_components = Vec::new();
+    for c in corpora::components(&session_arg, corpus, None, app_state).await? {
+        let ctype = c.get_type();
+        components_by_type
+            .entry(format!("{ctype:?}"))
+            .or_default()
+            .push(join_qname(&c.layer, &c.name));
+        // Only dominance and pointing relations have user-defined edge
+        // annotations. The backend can not address components with an empty
+        // layer or name.
+        if (ctype == AnnotationComponentType::Dominance
+            || ctype == AnnotationComponentType::Pointing)
+            && !c.layer.is_empty()
+            && !c.name.is_empty()
+        {
+            annotated_components.push(c);
+        }
+    }
+    let edge_keys = futures::future::try_join_all(
+        annotated_components
+            .iter()
+            .map(|c| corpora::edge_annotations(&session_arg, corpus, c, false, false, app_state)),
+    )
+    .await?;
+    let edge_summaries = annotated_components
+        .iter()
+        .zip(edge_keys)
+        .map(|(c, annos)| {
+            let session_arg = &session_arg;
+            async move {
+                let name = format!("{:?} {}", c.get_type(), join_qname(&c.layer, &c.name));
+                let summary = summarize_annotations(
+                    session_arg,
+                    corpus,
+                    annotation_keys(annos),
+                    Some(c),
+                    app_state,
+                )
+                .await;
+                (name, summary)
+            }
+        });
+    let edge_annotations: BTreeMap<_, _> = futures::future::join_all(edge_summaries)
+        .await
+        .into_iter()
+        .filter(|(_, summary)| !summary.is_empty())
+        .collect();
+
+    let inventory = AnnotationInventory {
+        node_annotations,
+        edge_annotations,
+        components_by_type,
+    };
+    app_state
+        .annotation_inventories
+        .insert(cache_key, inventory.clone());
+    Ok(inventory)
+}
+
+/// Get the metadata of a (sub-) corpus or document node. Annotations given
+/// in `annotation_order` are returned first, all others in alphabetical order.
+pub(crate) fn node_metadata(
+    g: &AnnotationGraph,
+    node: NodeID,
+    annotation_order: &[String],
+) -> Result<Vec<(String, String)>> {
+    let mut metadata: Vec<(String, String)> = g
+        .get_node_annos()
+        .get_annotations_for_item(&node)?
+        .into_iter()
+        .filter(|a| a.key.ns != ANNIS_NS)
+        .map(|a| (join_qname(&a.key.ns, &a.key.name), a.val.to_string()))
+        .collect();
+    metadata.sort_by_cached_key(|(name, _)| annotation_order_key(name, annotation_order));
+    Ok(metadata)
+}
+
+fn annotation_order_key(name: &str, annotation_order: &[String]) -> (usize, String) {
+    let position = annotation_order
+        .iter()
+        .position(|o| o == name)
+        .unwrap_or(annotation_order.len());
+    (position, name.to_string())
+}
+
+/// Returns all document nodes of a corpus graph, which are all corpus nodes
+/// that no other corpus node is part of.
+pub(crate) fn documents(g: &AnnotationGraph) -> Result<Vec<NodeID>> {
+    let part_of: Vec<_> = g
+        .get_all_components(Some(AnnotationComponentType::PartOf), None)
+        .into_iter()
+        .filter_map(|c| g.get_graphstorage(&c))
+        .collect();
+    let mut result = Vec::new();
+    for m in g.get_node_annos().exact_anno_search(
+        Some(ANNIS_NS),
+        "node_type",
+        ValueSearch::Some("corpus"),
+    ) {
+        let node = m?.node;
+        let mut is_document = true;
+        for gs in part_of.iter() {
+            if gs.get_ingoing_edges(node).next().is_some() {
+                is_document = false;
+                break;
+            }
+        }
+        if is_document {
+            result.push(node);
+        }
+    }
+    Ok(result)
+}
+
+async fn show_info(
+    session: Session,
+    Path(name): Path<String>,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    let session_arg = SessionArg::Session(session.clone());
+    let app_state = app_state.as_ref();
+
+    let config = corpora::configuration(&session_arg, &name, app_state).await?;
+    let token_count = search::count(
+        &session_arg,
+        &CountQuery {
+            query: "tok".to_string(),
+            corpora: vec![name.clone()],
+            query_language: QueryLanguage::AQL,
+        },
+        app_state,
+    )
+    .await?
+    .match_count;
+    let segmentations = corpora::segmentations(&session_arg, &name, app_state).await?;
+
+    // Get the metadata of the top-level corpus and the number of documents
+    let corpus_graph = corpora::corpus_graph(&session_arg, &name, app_state).await?;
+    let document_count = documents(&corpus_graph)?.len();
+    let metadata = if let Some(corpus_node) = corpus_graph.get_node_id_from_name(&name)? {
+        node_metadata(
+            &corpus_graph,
+            corpus_node,
+            &config.view.corpus_annotation_order,
+        )?
+    } else {
+        Vec::new()
+    };
+
+    let inventory = annotation_inventory(&session, &name, app_state).await?;
+
+    let html = app_state
+        .templates
+        .get_template("corpora/info.html")?
+        .render(context! {
+            name,
+            session => session,
+            token_count,
+            document_count,
+            metadata,
+            segmentations,
+            node_annotations => inventory.node_annotations,
+            edge_annotations => inventory.edge_annotations,
+            components_by_type => inventory.components_by_type,
+        })?;
+
+    Ok(Html(html))
+}
+
+#[derive(Deserialize, Debug)]
+struct DocumentListParams {
+    #[serde(default)]
+    filter: String,
+    /// The metadata field to sort by, the document name is used if not set.
+    sort: Option<String>,
+    #[serde(default)]
+    descending: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct DocumentEntry {
+    /// Name of the document node, relative to the corpus.
+    path: String,
+    name: String,
+    /// Names of the sub-corpora the document is part of.
+    sub_corpora: Vec<String>,
+    metadata: BTreeMap<String, String>,
+}
+
+impl DocumentEntry {
+    fn sort_key(&self, field: Option<&str>) -> String {
+        match field {
+            Some(field) => self
+                .metadata
+                .get(field)
+                .map(|v| v.to_lowercase())
+                .unwrap_or_default(),
+            None => self.name.to_lowercase(),
+        }
+    }
+
+    fn matches(&self, filter: &str) -> bool {
+        self.name.to_lowercase().contains(filter)
+            || self
+                .metadata
+                .values()
+                .any(|v| v.to_lowercase().contains(filter))
+    }
+}
+
+async fn show_documents(
+    session: Session,
+    Path(name): Path<String>,
+    Query(params): Query<DocumentListParams>,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    let session_arg = SessionArg::Session(session.clone());
+    let app_state = app_state.as_ref();
+
+    let config = corpora::configuration(&session_arg, &name, app_state).await?;
+    let corpus_graph = corpora::corpus_graph(&session_arg, &name, app_state).await?;
+
+    let mut fields: Vec<String> = Vec::new();
+    let mut documents = Vec::new();
+    for node in self::documents(&corpus_graph)? {
+        let Some(node_name) = corpus_graph
+            .get_node_annos()
+            .get_value_for_item(&node, &NODE_NAME_KEY)?
+        else {
+            continue;
+        };
+        let path = node_name
+            .strip_prefix(&format!("{name}/"))
+            .unwrap_or(&node_name)
+            .to_string();
+        let mut sub_corpora: Vec<String> = path.split('/').map(str::to_string).collect();
+        let document_name = sub_corpora.pop().unwrap_or_default();
+
+        let metadata = node_metadata(&corpus_graph, node, &config.view.corpus_annotation_order)?;
+        for (key, _) in &metadata {
+            if !fields.contains(key) {
+                fields.push(key.clone());
+            }
+        }
+        documents.push(DocumentEntry {
+            path,
+            name: document_name,
+            sub_corpora,
+            metadata: metadata.into_iter().collect(),
+        });
+    }
+    // Use the same order for the columns as for the metadata of a single document
+    fields.sort_by_cached_key(|name| {
+        annotation_order_key(name, &config.view.corpus_annotation_order)
+    });
+    let has_sub_corpora = documents.iter().any(|d| !d.sub_corpora.is_empty());
+
+    let filter = params.filter.to_lowercase();
+    documents.retain(|d| d.matches(&filter));
+    let sort = params.sort.filter(|field| fields.contains(field));
+    documents.sort_by_cached_key(|d| (d.sort_key(sort.as_deref()), d.path.clone()));
+    if params.descending {
+        documents.reverse();
+    }
+
+    let html = app_state
+        .templates
+        .get_template("corpora/documents.html")?
+        .render(context! {
+            name,
+            session => session,
+            documents,
+            fields,
+            has_sub_corpora,
+            filter => params.filter,
+            sort,
+            descending => params.descending,
+        })?;
+
+    Ok(Html(html))
+}
+
+#[serde_as]
+#[derive(Deserialize, Debug)]
+struct DocumentTextParams {
+    /// `None` if no segmentation was chosen and `Some(None)` if the tokens
+    /// were chosen explicitly.
+    #[serde(default)]
+    #[serde_as(as = "Option<NoneAsEmptyString>")]
+    segmentation: Option<Option<String>>,
+}
+
+#[derive(Serialize, Debug)]
+struct DocumentToken {
+    text: String,
+    whitespace_after: String,
+    /// The values of the annotation layers for this token, in the same order
+    /// as the layers.
+    glosses: Vec<Option<String>>,
+}
+
+async fn show_document_text(
+    session: Session,
+    Path((name, document)): Path<(String, String)>,
+    Query(params): Query<DocumentTextParams>,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    let session_arg = SessionArg::Session(session.clone());
+    let app_state = app_state.as_ref();
+
+    let segmentations = corpora::segmentations(&session_arg, &name, app_state).await?;
+    let config = corpora::configuration(&session_arg, &name, app_state).await?;
+    // Use the configured base text segmentation if the user did not choose one
+    let segmentation = params
+        .segmentation
+        .unwrap_or(config.view.base_text_segmentation)
+        .filter(|seg| segmentations.contains(seg));
+
+    let document_node_name = format!("{name}/{document}");
+    let g = corpora::document_graph(&session_arg, &name, &document_node_name, app_state).await?;
+
+    let tokens = converter::ordered_tokens(&g, segmentation.as_deref(), &Default::default())?;
+
+    // Collect the annotations of each token and of the spans covering the
+    // same base token
+    let coverage: Vec<_> = g
+        .get_all_components(Some(AnnotationComponentType::Coverage), None)
+        .into_iter()
+        .filter_map(|c| g.get_graphstorage(&c))
+        .collect();
+    let segmentation_key = segmentation
+        .as_ref()
+        .map(|seg| join_qname("default_ns", seg));
+    let hidden_annos: BTreeSet<&String> = config.view.hidden_annos.iter().collect();
+    let mut layers = BTreeSet::new();
+    let mut token_annos = Vec::with_capacity(tokens.len());
+    for t in tokens.iter() {
+        let mut covered_token = vec![t.node];
+        for gs in coverage.iter() {
+            for covered in gs.get_outgoing_edges(t.node) {
+                covered_token.push(covered?);
+            }
+        }
+        let mut annotated_nodes = BTreeSet::from([t.node]);
+        for token in covered_token {
+            for gs in coverage.iter() {
+                for span in gs.get_ingoing_edges(token) {
+                    annotated_nodes.insert(span?);
+                }
+            }
+        }
+        let mut annos: BTreeMap<String, String> = BTreeMap::new();
+        for n in annotated_nodes {
+            for a in g.get_node_annos().get_annotations_for_item(&n)? {
+                let qname = join_qname(&a.key.ns, &a.key.name);
+                if a.key.ns != ANNIS_NS
+                    && Some(&qname) != segmentation_key.as_ref()
+                    && !hidden_annos.contains(&qname)
+                {
+                    layers.insert(qname.clone());
+                    annos.entry(qname).or_insert_with(|| a.val.to_string());
+                }
+            }
+        }
+        token_annos.push(annos);
+    }
+
+    let token_value_key = AnnoKey {
+        ns: ANNIS_NS.into(),
+        name: "tok".into(),
+    };
+    let whitespace_after_key = AnnoKey {
+        ns: ANNIS_NS.into(),
+        name: "tok-whitespace-after".into(),
+    };
+    let mut text = Vec::with_capacity(tokens.len());
+    for (t, mut annos) in tokens.into_iter().zip(token_annos) {
+        let value = g
+            .get_node_annos()
+            .get_value_for_item(&t.node, &token_value_key)?;
+        let whitespace_after = g
+            .get_node_annos()
+            .get_value_for_item(&t.node, &whitespace_after_key)?;
+        text.push(DocumentToken {
+            text: value.unwrap_or_default().to_string(),
+            whitespace_after: whitespace_after
+                .map(|ws| ws.to_string())
+                .unwrap_or_else(|| " ".to_string()),
+            glosses: layers.iter().map(|l| annos.remove(l)).collect(),
+        });
+    }
+
+    let html = app_state
+        .templates
+        .get_template("corpora/document.html")?
+        .render(context! {
+            name,
+            document,
+            session => session,
+            segmentations,
+            segmentation,
+            layers,
+            text,
+        })?;
+
+    Ok(Html(html))
+}
+
+#[derive(Deserialize, Debug)]
+struct DiscourseParams {
+    /// Qualified name of the pointing component with the coreference relations.
+    pointing: Option<String>,
+}
+
+/// Parts of component names that indicate coreference relations.
+const COREFERENCE_NAMES: &[&str] = &["coref", "anaphor", "antecedent"];
+
+/// Find the position of the pointing component that most likely contains the
+/// coreference relations. The layer of a discourse visualizer in the corpus
+/// configuration is preferred, then components with a coreference-like name.
+fn default_coreference_component(
+    components: &[AnnotationComponent],
+    config: &CorpusConfiguration,
+) -> usize {
+    let configured_layers: Vec<&str> = config
+        .visualizers
+        .iter()
+        .filter(|rule| rule.vis_type == "discourse")
+        .filter_map(|rule| rule.layer.as_deref())
+        .collect();
+    components
+        .iter()
+        .position(|c| configured_layers.contains(&c.layer.as_str()))
+        .or_else(|| {
+            components.iter().position(|c| {
+                let name = c.name.to_lowercase();
+                COREFERENCE_NAMES.iter().any(|n| name.contains(n))
+            })
+        })
+        .unwrap_or_default()
+}
+
+async fn show_document_discourse(
+    session: Session,
+    Path((name, document)): Path<(String, String)>,
+    Query(params): Query<DiscourseParams>,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    let session_arg = SessionArg::Session(session.clone());
+    let app_state = app_state.as_ref();
+
+    let document_node_name = format!("{name}/{document}");
+    let g = corpora::document_graph(&session_arg, &name, &document_node_name, app_state).await?;
+
+    let mut pointing_components = arcs::pointing_components(&g);
+    pointing_components.sort_by_cached_key(|c| join_qname(&c.layer, &c.name));
+    let pointing_names: Vec<String> = pointing_components
+        .iter()
+        .map(|c| join_qname(&c.layer, &c.name))
+        .collect();
+    let requested_pointing = params
+        .pointing
+        .as_ref()
+        .and_then(|selected| pointing_names.iter().position(|n| n == selected));
+    let selected_pointing = if let Some(requested_pointing) = requested_pointing {
+        requested_pointing
+    } else {
+        let config = corpora::configuration(&session_arg, &name, app_state).await?;
+        default_coreference_component(&pointing_components, &config)
+    };
+    let selected_component: Vec<_> = pointing_components
+        .get(selected_pointing)
+        .cloned()
+        .into_iter()
+        .collect();
+    let discourse = discourse::create(&g, &selected_component)?;
+
+    let html = app_state
+        .templates
+        .get_template("corpora/discourse.html")?
+        .render(context! {
+            name,
+            document,
+            session => session,
+            pointing_names,
+            selected_pointing => pointing_names.get(selected_pointing),
+            discourse,
+        })?;
+
+    Ok(Html(html))
+}
+
+/// Pass through a file, e.g. a media file, linked to the corpus from the
+/// backend. Range requests are forwarded, so browsers can seek in media files.
+async fn get_file(
+    session: Session,
+    Path((name, file)): Path<(String, String)>,
+    headers: HeaderMap,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    let response = corpora::file(
+        &SessionArg::Session(session),
+        &name,
+        &file,
+        headers.get(header::RANGE),
+        &app_state,
+    )
+    .await?;
+
+    let mut response_headers = HeaderMap::new();
+    for key in [
+        header::CONTENT_TYPE,
+        header::CONTENT_LENGTH,
+        header::CONTENT_RANGE,
+        header::ACCEPT_RANGES,
+    ] {
+        if let Some(value) = response.headers().get(&key) {
+            response_headers.insert(key, value.clone());
+        }
+    }
+    if !response_headers.contains_key(header::CONTENT_TYPE) {
+        let mime_type = mime_guess::from_path(&file).first_or_octet_stream();
+        response_headers.insert(header::CONTENT_TYPE, mime_type.as_ref().parse()?);
+    }
+    let status = response.status();
+    let body = StreamBody::new(response.bytes_stream());
+
+    Ok((status, response_headers, body))
+}
+
 #[cfg(test)]
 mod tests;
@@ -1,13 +1,14 @@
 use crate::{
     config::CliConfig,
-    tests::{get_body, start_end2end_servers},
+    tests::{get_body, get_html, start_end2end_servers},
 };
 use axum::{
     body::Body,
     http::{Request, StatusCode},
 };
 use fantoccini::Locator;
-use mockito::Server;
+use mockito::{Matcher, Server};
+use scraper::Selector;
 use std::time::Duration;
 use test_log::test;
 use tower::ServiceExt;
@@ -310,3 +311,539 @@ async fn service_down() {
     }
     m.assert();
 }
+
+#[test(tokio::test)]
+async fn corpus_info_page() {
+    let mut service_mock = Server::new_with_port(0);
+    let mocks = vec![
+        service_mock
+            .mock("GET", "/corpora/pcc2/configuration")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"view": {"corpus_annotation_order": ["version", "full_name"]}}"#)
+            .expect(2)
+            .create(),
+        service_mock
+            .mock("POST", "/search/count")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"query": "tok", "corpora": ["pcc2"]}"#.into(),
+            ))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"match_count": 399, "document_count": 2}"#)
+            .expect(2)
+            .create(),
+        service_mock
+            .mock("GET", "/corpora/pcc2/components")
+            .match_query(Matcher::UrlEncoded("type".into(), "Ordering".into()))
+            .with_header("content-type", "application/json")
+            .with_body(r#"[{"type": "Ordering", "name": "", "layer": "annis"}]"#)
+            .expect(2)
+            .create(),
+        service_mock
+            .mock("GET", "/corpora/pcc2/components")
+            .match_query(Matcher::Exact("".into()))
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"[
+                    {"type": "Ordering", "name": "", "layer": "annis"},
+                    {"type": "Dominance", "name": "edge", "layer": "tiger"},
+                    {"type": "Dominance", "name": "", "layer": "tiger"},
+                    {"type": "Pointing", "name": "dep", "layer": "dep"}
+                ]"#,
+            )
+            .expect(1)
+            .create(),
+        // Only the annotation keys are fetched and only once, since the
+        // annotations are cached
+        service_mock
+            .mock("GET", "/corpora/pcc2/node-annotations")
+            .match_query(Matcher::UrlEncoded("list_values".into(), "false".into()))
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"[
+                    {"key": {"ns": "annis", "name": "tok"}, "val": ""},
+                    {"key": {"ns": "tiger", "name": "pos"}, "val": ""},
+                    {"key": {"ns": "tiger", "name": "lemma"}, "val": ""}
+                ]"#,
+            )
+            .expect(1)
+            .create(),
+        service_mock
+            .mock("POST", "/search/count")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"query": "tiger:pos", "corpora": ["pcc2"]}"#.into(),
+            ))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"match_count": 399, "document_count": 2}"#)
+            .expect(1)
+            .create(),
+        service_mock
+            .mock("POST", "/search/count")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"query": "tiger:lemma", "corpora": ["pcc2"]}"#.into(),
+            ))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"match_count": 398, "document_count": 2}"#)
+            .expect(1)
+            .create(),
+        service_mock
+            .mock("POST", "/search/count")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"query": "node >edge[tiger:func=/.*/] node", "corpora": ["pcc2"]}"#.into(),
+            ))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"match_count": 250, "document_count": 2}"#)
+            .expect(1)
+            .create(),
+        // Annotations that can not be counted are shown without a count
+        service_mock
+            .mock("POST", "/search/count")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"query": "node ->dep[dep:func=/.*/] node", "corpora": ["pcc2"]}"#.into(),
+            ))
+            .with_status(500)
+            .expect(1)
+            .create(),
+        service_mock
+            .mock(
+                "GET",
+                "/corpora/pcc2/edge-annotations/Dominance/tiger/edge/",
+            )
+            .match_query(Matcher::UrlEncoded("list_values".into(), "false".into()))
+            .with_header("content-type", "application/json")
+            .with_body(r#"[{"key": {"ns": "tiger", "name": "func"}, "val": ""}]"#)
+            .expect(1)
+            .create(),
+        service_mock
+            .mock("GET", "/corpora/pcc2/edge-annotations/Pointing/dep/dep/")
+            .match_query(Matcher::UrlEncoded("list_values".into(), "false".into()))
+            .with_header("content-type", "application/json")
+            .with_body(r#"[{"key": {"ns": "dep", "name": "func"}, "val": ""}]"#)
+            .expect(1)
+            .create(),
+        service_mock
+            .mock("GET", "/corpora/pcc2/subgraph-for-query")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("query".into(), "annis:node_type=\"corpus\"".into()),
+                Matcher::UrlEncoded("component_type_filter".into(), "PartOf".into()),
+            ]))
+            .with_body_from_file("tests/pcc2-corpus-graph.graphml")
+            .expect(2)
+            .create(),
+    ];
+
+    let config = CliConfig {
+        service_url: service_mock.url(),
+        ..Default::default()
+    };
+    let app = crate::app(&config, chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+
+    // The second request uses the cached annotations
+    for _ in 0..2 {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/corpora/pcc2")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let html = get_html(response).await;
+
+        let text_of = |selector: &str| -> Vec<String> {
+            html.select(&Selector::parse(selector).unwrap())
+                .map(|e| e.text().collect::<String>().trim().to_string())
+                .collect()
+        };
+
+        assert_eq!(vec!["399"], text_of("#token-count"));
+        assert_eq!(vec!["2"], text_of("#document-count"));
+        assert_eq!(
+            vec!["version", "full_name", "language", "source"],
+            text_of("#corpus-metadata th")
+        );
+        assert_eq!(
+            vec!["tiger::lemma", "398", "tiger::pos", "399"],
+            text_of("#corpus-node-annotations tbody td")
+        );
+        assert_eq!(
+            vec![
+                "Dominance tiger::edge",
+                "tiger::func",
+                "250",
+                "Pointing dep::dep",
+                "dep::func",
+                ""
+            ],
+            text_of("#corpus-edge-annotations tbody td")
+        );
+        assert_eq!(
+            vec!["Dominance", "Ordering", "Pointing"],
+            text_of("#corpus-components th")
+        );
+    }
+
+    for m in mocks {
+        m.assert();
+    }
+}
+
+#[test(tokio::test)]
+async fn document_list_sorted_and_filtered() {
+    let mut service_mock = Server::new_with_port(0);
+    let config_mock = service_mock
+        .mock("GET", "/corpora/pcc2/configuration")
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"view": {"corpus_annotation_order": ["Titel"]}}"#)
+        .expect(2)
+        .create();
+    let graph_mock = service_mock
+        .mock("GET", "/corpora/pcc2/subgraph-for-query")
+        .match_query(Matcher::Any)
+        .with_body_from_file("tests/pcc2-corpus-graph.graphml")
+        .expect(2)
+        .create();
+
+    let config = CliConfig {
+        service_url: service_mock.url(),
+        ..Default::default()
+    };
+    let app = crate::app(&config, chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/corpora/pcc2/documents?sort=Genre&descending=true")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = get_html(response).await;
+
+    let header: Vec<_> = html
+        .select(&Selector::parse("#corpus-documents th").unwrap())
+        .map(|th| th.text().collect::<String>().trim().to_string())
+        .collect();
+    assert_eq!(vec!["Document", "Titel", "Dokumentname", "Genre ▼"], header);
+    let links: Vec<_> = html
+        .select(&Selector::parse("#corpus-documents td.document-name a").unwrap())
+        .map(|a| {
+            (
+                a.inner_html(),
+                a.value().attr("href").unwrap_or_default().to_string(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            (
+                "4282".to_string(),
+                "http://127.0.0.1:3000/corpora/pcc2/documents/4282".to_string()
+            ),
+            (
+                "11299".to_string(),
+                "http://127.0.0.1:3000/corpora/pcc2/documents/11299".to_string()
+            )
+        ],
+        links
+    );
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/corpora/pcc2/documents?filter=politik")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = get_html(response).await;
+    let documents: Vec<_> = html
+        .select(&Selector::parse("#corpus-documents td.document-name a").unwrap())
+        .map(|a| a.inner_html())
+        .collect();
+    assert_eq!(vec!["11299"], documents);
+
+    config_mock.assert();
+    graph_mock.assert();
+}
+
+#[test(tokio::test)]
+async fn document_text_with_segmentation() {
+    let mut service_mock = Server::new_with_port(0);
+    let mocks = vec![
+        service_mock
+            .mock("GET", "/corpora/RIDGES_Herbology_Version9.0/components")
+            .match_query(Matcher::UrlEncoded("type".into(), "Ordering".into()))
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"[
+                    {"type": "Ordering", "name": "", "layer": "annis"},
+                    {"type": "Ordering", "name": "dipl", "layer": "default_ns"},
+                    {"type": "Ordering", "name": "norm", "layer": "default_ns"}
+                ]"#,
+            )
+            .expect(2)
+            .create(),
+        service_mock
+            .mock("GET", "/corpora/RIDGES_Herbology_Version9.0/configuration")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"view": {"base_text_segmentation": "dipl"}}"#)
+            .expect(2)
+            .create(),
+        service_mock
+            .mock("GET", "/corpora/RIDGES_Herbology_Version9.0/subgraph-for-query")
+            .match_query(Matcher::UrlEncoded(
+                "query".into(),
+                "annis:node_type=\"node\" @* annis:node_name=\"RIDGES_Herbology_Version9.0/Experimenta_1550_Schellenberg\"".into(),
+            ))
+            .with_body_from_file("tests/ridges-subgraph.graphml")
+            .expect(2)
+            .create(),
+    ];
+
+    let config = CliConfig {
+        service_url: service_mock.url(),
+        ..Default::default()
+    };
+    let app = crate::app(&config, chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/corpora/RIDGES_Herbology_Version9.0/documents/Experimenta_1550_Schellenberg")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = get_html(response).await;
+
+    let selected_segmentation: Vec<_> = html
+        .select(&Selector::parse("select[name='segmentation'] option[selected]").unwrap())
+        .map(|o| o.inner_html())
+        .collect();
+    assert_eq!(vec!["dipl"], selected_segmentation);
+
+    let text: Vec<_> = html
+        .select(&Selector::parse("#document-text .token-text").unwrap())
+        .map(|t| t.inner_html())
+        .collect();
+    assert_eq!(vec!["Baldrian", "waſſer", "zůtrincken", "geben"], text);
+
+    let layers: Vec<_> = html
+        .select(&Selector::parse("#document-layers input").unwrap())
+        .map(|i| i.value().attr("value").unwrap_or_default().to_string())
+        .collect();
+    assert_eq!(
+        vec![
+            "default_ns::abbr",
+            "default_ns::clean",
+            "default_ns::lang",
+            "default_ns::lb",
+            "default_ns::lemma",
+            "default_ns::norm",
+            "default_ns::pb",
+            "default_ns::pos",
+            "default_ns::quote",
+            "default_ns::script"
+        ],
+        layers
+    );
+    let pos: Vec<_> = html
+        .select(&Selector::parse("#document-text .gloss-7").unwrap())
+        .map(|t| t.inner_html())
+        .collect();
+    assert_eq!(vec!["NN", "NN", "PTKZU", "VVINF"], pos);
+
+    // Choosing the tokens must not fall back to the configured segmentation
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/corpora/RIDGES_Herbology_Version9.0/documents/Experimenta_1550_Schellenberg?segmentation=")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = get_html(response).await;
+    let selected_segmentation: Vec<_> = html
+        .select(&Selector::parse("select[name='segmentation'] option[selected]").unwrap())
+        .map(|o| o.inner_html())
+        .collect();
+    assert!(selected_segmentation.is_empty());
+
+    for m in mocks {
+        m.assert();
+    }
+}
+
+#[test(tokio::test)]
+async fn document_coreference_chains() {
+    let mut service_mock = Server::new_with_port(0);
+    let m = service_mock
+        .mock("GET", "/corpora/corefcorpus/subgraph-for-query")
+        .match_query(Matcher::UrlEncoded(
+            "query".into(),
+            "annis:node_type=\"node\" @* annis:node_name=\"corefcorpus/doc1\"".into(),
+        ))
+        .with_body_from_file("tests/coreference-document.graphml")
+        .create();
+
+    let config = CliConfig {
+        service_url: service_mock.url(),
+        ..Default::default()
+    };
+    let app = crate::app(&config, chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/corpora/corefcorpus/documents/doc1/discourse?pointing=mmax%3A%3Aanaphor_antecedent")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = get_html(response).await;
+
+    let options: Vec<_> = html
+        .select(&Selector::parse("select[name='pointing'] option").unwrap())
+        .map(|o| (o.inner_html(), o.value().attr("selected").is_some()))
+        .collect();
+    assert_eq!(
+        vec![
+            ("dep::dep".to_string(), false),
+            ("mmax::anaphor_antecedent".to_string(), true)
+        ],
+        options
+    );
+
+    // Each token with the chains it is part of
+    let token: Vec<(String, Vec<String>)> = html
+        .select(&Selector::parse("#discourse-text .discourse-token").unwrap())
+        .map(|t| {
+            let text = t
+                .select(&Selector::parse(".token-text").unwrap())
+                .map(|e| e.inner_html())
+                .collect();
+            let chains = t
+                .select(&Selector::parse(".chain-underline").unwrap())
+                .filter_map(|e| {
+                    e.value()
+                        .classes()
+                        .find(|c| c.starts_with("chain-") && *c != "chain-underline")
+                        .map(str::to_string)
+                })
+                .collect();
+            (text, chains)
+        })
+        .collect();
+    let chains = |chains: &[&str]| chains.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            ("Peter".to_string(), chains(&["chain-0"])),
+            ("traf".to_string(), chains(&[])),
+            ("Maria".to_string(), chains(&["chain-1"])),
+            (".".to_string(), chains(&[])),
+            ("Er".to_string(), chains(&["chain-0"])),
+            ("grüßte".to_string(), chains(&[])),
+            ("sie".to_string(), chains(&["chain-1"])),
+            (".".to_string(), chains(&[])),
+        ],
+        token
+    );
+
+    m.assert();
+}
+
+#[test(tokio::test)]
+async fn document_coreference_default_component() {
+    let mut service_mock = Server::new_with_port(0);
+    let graph_mock = service_mock
+        .mock("GET", "/corpora/corefcorpus/subgraph-for-query")
+        .match_query(Matcher::Any)
+        .with_body_from_file("tests/coreference-document.graphml")
+        .expect(2)
+        .create();
+    let config_mock = service_mock
+        .mock("GET", "/corpora/corefcorpus/configuration")
+        .with_header("content-type", "application/json")
+        .with_body("{}")
+        .create();
+
+    let config = CliConfig {
+        service_url: service_mock.url(),
+        ..Default::default()
+    };
+    let app = crate::app(&config, chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+
+    let selected_option = |html: scraper::Html| -> Vec<String> {
+        html.select(&Selector::parse("select[name='pointing'] option[selected]").unwrap())
+            .map(|o| o.inner_html())
+            .collect()
+    };
+
+    // The component with a coreference-like name is preferred over the first one
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/corpora/corefcorpus/documents/doc1/discourse")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        vec!["mmax::anaphor_antecedent"],
+        selected_option(get_html(response).await)
+    );
+    config_mock.assert();
+
+    // The layer of a configured discourse visualizer has precedence
+    config_mock.remove();
+    let config_mock = service_mock
+        .mock("GET", "/corpora/corefcorpus/configuration")
+        .with_header("content-type", "application/json")
+        .with_body(
+            r#"{"visualizers": [{"layer": "dep", "vis_type": "discourse", "display_name": "Coreference"}]}"#,
+        )
+        .create();
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/corpora/corefcorpus/documents/doc1/discourse")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(vec!["dep::dep"], selected_option(get_html(response).await));
+
+    config_mock.assert();
+    graph_mock.assert();
+}
@@ -0,0 +1,215 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::Arc,
+};
+
+use axum::{
+    extract::{Query, State},
+    response::{Html, IntoResponse},
+    routing::get,
+    Router,
+};
+use graphannis::corpusstorage::{FrequencyDefEntry, QueryLanguage, ResultOrder};
+use graphannis_core::graph::{ANNIS_NS, NODE_NAME_KEY};
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, NoneAsEmptyString};
+
+use crate::{
+    client::{
+        corpora,
+        search::{self, FindQuery, FrequencyQuery},
+    },
+    state::{GlobalAppState, Session, SessionArg},
+    views::corpora::{documents, node_metadata},
+    Result,
+};
+
+/// The match counts are normalized to the number of matches per this many
+/// token.
+const NORMALIZATION_BASE: f64 = 1000.0;
+
+/// Maximum number of matches that are assigned to their documents.
+pub const MAX_DISTRIBUTED_MATCHES: usize = 10_000;
+
+pub fn create_routes() -> Result<Router<Arc<GlobalAppState>>> {
+    let result = Router::new().route("/", get(show_page));
+    Ok(result)
+}
+
+#[serde_as]
+#[derive(Deserialize, Debug)]
+struct FormParams {
+    query: Option<String>,
+    #[serde(default)]
+    query_language: QueryLanguage,
+    /// Qualified name of the metadata to group the documents by.
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    meta: Option<String>,
+}
+
+/// The number of matches in a document or in all documents with the same
+/// metadata value.
+#[derive(Serialize, Debug, Default, Clone, PartialEq)]
+struct DistributionEntry {
+    /// Document node name or metadata value.
+    name: String,
+    documents: usize,
+    matches: usize,
+    token_count: usize,
+    /// Matches per [`NORMALIZATION_BASE`] token.
+    relative_frequency: f64,
+}
+
+impl DistributionEntry {
+    fn add(&mut self, matches: usize, token_count: usize) {
+        self.documents += 1;
+        self.matches += matches;
+        self.token_count += token_count;
+        if self.token_count > 0 {
+            self.relative_frequency =
+                self.matches as f64 * NORMALIZATION_BASE / self.token_count as f64;
+        }
+    }
+}
+
+/// Sort the entries by their number of matches, most frequent first.
+fn sort_entries(entries: &mut [DistributionEntry]) {
+    entries.sort_by(|a, b| b.matches.cmp(&a.matches).then_with(|| a.name.cmp(&b.name)));
+}
+
+/// Show the form and, if a query is given, how the matches are distributed
+/// over the documents of the selected corpora and the values of the chosen
+/// metadata.
+async fn show_page(
+    session: Session,
+    Query(params): Query<FormParams>,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    let session_arg = SessionArg::Session(session.clone());
+    let app_state = app_state.as_ref();
+    let query = params.query.clone().unwrap_or_default();
+    let corpora: Vec<String> = session.selected_corpora().iter().cloned().collect();
+
+    // Collect the metadata of all documents
+    let mut document_metadata: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+    for corpus in corpora.iter() {
+        let config = corpora::configuration(&session_arg, corpus, app_state).await?;
+        let corpus_graph = corpora::corpus_graph(&session_arg, corpus, app_state).await?;
+        for node in documents(&corpus_graph)? {
+            if let Some(node_name) = corpus_graph
+                .get_node_annos()
+                .get_value_for_item(&node, &NODE_NAME_KEY)?
+            {
+                let metadata =
+                    node_metadata(&corpus_graph, node, &config.view.corpus_annotation_order)?;
+                document_metadata.insert(node_name.to_string(), metadata.into_iter().collect());
+            }
+        }
+    }
+    let meta_keys: BTreeSet<&String> = document_metadata.values().flat_map(|m| m.keys()).collect();
+
+    let mut by_document = Vec::new();
+    let mut by_value = Vec::new();
+    let mut match_count = 0;
+    let mut limited = false;
+    if !query.trim().is_empty() && !corpora.is_empty() {
+        // The node names of the matches start with the document path. One
+        // additional match is requested to know whether there are more
+        // matches than included.
+        let find_query = FindQuery {
+            query: query.clone(),
+            corpora: corpora.clone(),
+            query_language: params.query_language,
+            limit: Some(MAX_DISTRIBUTED_MATCHES as u64 + 1),
+            offset: 0,
+            order: ResultOrder::Normal,
+        };
+        let mut matches_per_document: HashMap<String, usize> = HashMap::new();
+        for m in search::find(&session_arg, &find_query, app_state)
+            .await?
+            .range(..)?
+        {
+            let (idx, node_ids) = m?;
+            if idx as usize >= MAX_DISTRIBUTED_MATCHES {
+                limited = true;
+                break;
+            }
+            if let Some((document, _)) = node_ids.first().and_then(|id| id.split_once('#')) {
+                *matches_per_document
+                    .entry(document.to_string())
+                    .or_default() += 1;
+                match_count += 1;
+            }
+        }
+
+        let token_query = FrequencyQuery {
+            query: "tok @* annis:doc".to_string(),
+            corpora: corpora.clone(),
+            query_language: QueryLanguage::AQL,
+            definition: vec![FrequencyDefEntry {
+                ns: Some(ANNIS_NS.to_string()),
+                name: "node_name".to_string(),
+                node_ref: "2".to_string(),
+            }],
+        };
+        let token_per_document: HashMap<String, usize> =
+            search::frequency(&session_arg, &token_query, app_state)
+                .await?
+                .into_iter()
+                .filter_map(|row| Some((row.values.into_iter().next()?, row.count)))
+                .collect();
+
+        let mut values: BTreeMap<String, DistributionEntry> = BTreeMap::new();
+        for (document, metadata) in document_metadata.iter() {
+            let matches = matches_per_document
+                .get(document)
+                .copied()
+                .unwrap_or_default();
+            let token_count = token_per_document
+                .get(document)
+                .copied()
+                .unwrap_or_default();
+            let mut entry = DistributionEntry {
+                name: document.clone(),
+                ..Default::default()
+            };
+            entry.add(matches, token_count);
+            by_document.push(entry);
+
+            if let Some(meta) = &params.meta {
+                let value = metadata.get(meta).cloned().unwrap_or_default();
+                values
+                    .entry(value.clone())
+                    .or_insert_with(|| DistributionEntry {
+                        name: value,
+                        ..Default::default()
+                    })
+                    .add(matches, token_count);
+            }
+        }
+        by_value = values.into_values().collect();
+        sort_entries(&mut by_document);
+        sort_entries(&mut by_value);
+    }
+
+    let html = app_state
+        .templates
+        .get_template("distribution.html")?
+        .render(context! {
+            session => session,
+            query,
+            query_language => params.query_language,
+            meta => params.meta,
+            meta_keys,
+            match_count,
+            limited,
+            by_document,
+            by_value,
+        })?;
+    Ok(Html(html))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,146 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use mockito::{Matcher, Server};
+use scraper::{Html, Selector};
+use test_log::test;
+use tower::ServiceExt;
+
+use super::MAX_DISTRIBUTED_MATCHES;
+use crate::{
+    config::CliConfig,
+    state::GlobalAppState,
+    tests::{create_session_with_corpora, get_html},
+};
+
+/// Get the distribution page for the pcc2 corpus, with a backend that
+/// returns the given matches.
+async fn distribution_page(uri: &str, find_body: &str, expected_find: &str) -> Html {
+    let mut service_mock = Server::new_with_port(0);
+    service_mock
+        .mock("GET", "/corpora/pcc2/configuration")
+        .with_header("content-type", "application/json")
+        .with_body("{}")
+        .create();
+    service_mock
+        .mock("GET", "/corpora/pcc2/subgraph-for-query")
+        .match_query(Matcher::UrlEncoded(
+            "query".into(),
+            "annis:node_type=\"corpus\"".into(),
+        ))
+        .with_body_from_file("tests/pcc2-corpus-graph.graphml")
+        .create();
+    let find_mock = service_mock
+        .mock("POST", "/search/find")
+        .match_body(Matcher::PartialJsonString(expected_find.to_string()))
+        .with_header("content-type", "text/plain")
+        .with_body(find_body)
+        .create();
+    service_mock
+        .mock("POST", "/search/frequency")
+        .match_body(Matcher::PartialJsonString(
+            r#"{"query": "tok @* annis:doc"}"#.to_string(),
+        ))
+        .with_header("content-type", "application/json")
+        .with_body(
+            r#"[
+                {"values": ["pcc2/4282"], "count": 200},
+                {"values": ["pcc2/11299"], "count": 50}
+            ]"#,
+        )
+        .create();
+
+    let config = CliConfig {
+        service_url: service_mock.url(),
+        ..Default::default()
+    };
+    let (session_cookie, session_store) = create_session_with_corpora(&["pcc2"]).await;
+    let app_state = Arc::new(GlobalAppState::new(&config).unwrap());
+    let app = crate::app_with_state(app_state, session_store, chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(uri)
+                .header("Cookie", session_cookie)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    find_mock.assert();
+    get_html(response).await
+}
+
+#[test(tokio::test)]
+async fn distribution_by_document_and_metadata() {
+    let html = distribution_page(
+        "/distribution?query=tok&query_language=AQLQuirksV3&meta=Genre",
+        "pcc2/4282#tok_1\npcc2/4282#tok_2\npcc2/11299#tok_5\n",
+        &format!(
+            r#"{{"query": "tok", "query_language": "AQLQuirksV3", "limit": {}}}"#,
+            MAX_DISTRIBUTED_MATCHES + 1
+        ),
+    )
+    .await;
+
+    let column = |html: &Html, table: &str, column: &str| -> Vec<String> {
+        html.select(&Selector::parse(&format!("#{table} td.distribution-{column}")).unwrap())
+            .map(|td| td.text().collect::<String>().trim().to_string())
+            .collect()
+    };
+
+    assert_eq!(
+        vec!["pcc2/4282", "pcc2/11299"],
+        column(&html, "distribution-by-document", "name")
+    );
+    assert_eq!(
+        vec!["2", "1"],
+        column(&html, "distribution-by-document", "matches")
+    );
+    assert_eq!(
+        vec!["10.0", "20.0"],
+        column(&html, "distribution-by-document", "relative")
+    );
+    assert_eq!(
+        vec!["Sport", "Politik"],
+        column(&html, "distribution-by-value", "name")
+    );
+    // All metadata keys of the documents can be chosen
+    let options: Vec<_> = html
+        .select(&Selector::parse("select[name=meta] option").unwrap())
+        .map(|o| o.value().attr("value").unwrap_or_default().to_string())
+        .collect();
+    assert_eq!(vec!["", "Dokumentname", "Genre", "Titel"], options);
+    assert!(html
+        .select(&Selector::parse(".notification.is-warning").unwrap())
+        .next()
+        .is_none());
+}
+
+#[test(tokio::test)]
+async fn distribution_of_limited_matches() {
+    let matches: String = (0..=MAX_DISTRIBUTED_MATCHES)
+        .map(|i| format!("pcc2/4282#tok_{i}\n"))
+        .collect();
+    let html = distribution_page("/distribution?query=tok", &matches, "{}").await;
+
+    let summary = html
+        .select(&Selector::parse("#distribution-summary").unwrap())
+        .next()
+        .map(|p| p.inner_html());
+    assert_eq!(
+        Some(format!("{MAX_DISTRIBUTED_MATCHES} matches in 2 documents.")),
+        summary
+    );
+    assert!(html
+        .select(&Selector::parse(".notification.is-warning").unwrap())
+        .next()
+        .is_some());
+}
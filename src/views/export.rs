@@ -1,10 +1,17 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, io::Write, sync::Arc};
 
 use crate::{
-    client::{self, search::FindQuery},
+    citation::Citation,
+    client::{
+        self,
+        search::{self, CountQuery, FindQuery},
+    },
+    concordance::ContextPosition,
     converter::{CSVConfig, CSVExporter},
     errors::AppError,
-    state::{ExportJob, GlobalAppState, Session, SessionArg},
+    history::{self, HistoryEntry},
+    state::{ExportJob, GlobalAppState, JobState, Session, SessionArg},
+    views::permalink::Permalink,
     Result,
 };
 use axum::{
@@ -15,13 +22,16 @@ use axum::{
     routing::{delete, get, post},
     Form, Router,
 };
+use dashmap::mapref::entry::Entry;
 use graphannis::corpusstorage::{QueryLanguage, ResultOrder};
 use minijinja::context;
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
+use serde_with::{serde_as, DisplayFromStr};
 use tempfile::NamedTempFile;
 use tokio::sync::mpsc::channel;
 use tokio::task::JoinHandle;
 use tokio_util::io::ReaderStream;
+use tracing::warn;
 
 const DEFAULT_EXAMPLE: &str = r#"text,tiger::lemma (1),tiger::morph (1),tiger::pos (1)
 Feigenblatt,Feigenblatt,Nom.Sg.Neut,NN
@@ -34,17 +44,38 @@ pub fn create_routes() -> Result<Router<Arc<GlobalAppState>>> {
         .route("/job", post(create_job))
         .route("/job", get(job_status))
         .route("/job", delete(cancel_job))
-        .route("/file", get(download_file));
+        .route("/file", get(download_file))
+        .route("/citation", get(show_citation));
     Ok(result)
 }
 
+#[serde_as]
 #[derive(Deserialize, Debug)]
 struct FormParams {
     query: Option<String>,
+    #[serde(default)]
+    query_language: QueryLanguage,
+    /// Write the citation of the query and corpora as header of the exported
+    /// file.
+    #[serde(default)]
+    #[serde_as(as = "DisplayFromStr")]
+    include_citation: bool,
     #[serde(flatten)]
     config: CSVConfig,
 }
 
+impl FormParams {
+    fn permalink_url(&self, session: &Session, state: &GlobalAppState) -> String {
+        Permalink::new(
+            self.query.as_deref().unwrap_or_default(),
+            self.query_language,
+            &self.config,
+            session,
+        )
+        .url(&state.frontend_prefix)
+    }
+}
+
 async fn show_page(
     session: Session,
     Query(params): Query<FormParams>,
@@ -56,15 +87,49 @@ async fn show_page(
         Ok(DEFAULT_EXAMPLE.to_string())
     };
 
-    let default_context_sizes = vec![0, 1, 5, 10, 20];
+    // Also offer the context sizes given as parameter, e.g. by a permalink
+    let mut default_context_sizes = vec![0, 1, 5, 10, 20];
+    for ctx in [params.config.left_context, params.config.right_context] {
+        if !default_context_sizes.contains(&ctx) {
+            default_context_sizes.push(ctx);
+        }
+    }
+    default_context_sizes.sort_unstable();
+    // The configuration is serialized with the context sizes as strings
+    let default_context_sizes: Vec<String> = default_context_sizes
+        .into_iter()
+        .map(|ctx| ctx.to_string())
+        .collect();
+
+    let segmentations = common_segmentations(&session, &state).await;
+
+    let result = state
+        .templates
+        .get_template("export.html")?
+        .render(context! {
+            example,
+            session => session,
+            job => current_job(&session, &state),
+            query => params.query,
+            query_language => params.query_language,
+            permalink => params.query.as_ref().map(|_| params.permalink_url(&session, &state)),
+            config => params.config,
+            default_context_sizes,
+            segmentations,
+            sort_positions => ContextPosition::ALL.map(|p| p.to_string()),
+        })?;
+
+    Ok(Html(result))
+}
 
-    // Find all segmentations that exist in all of the corpora
+/// Find all segmentations that exist in all of the selected corpora.
+pub(crate) async fn common_segmentations(session: &Session, state: &GlobalAppState) -> Vec<String> {
     let number_collected_corpora = session.selected_corpora().len();
     let mut all_segmentations: HashMap<String, usize> = HashMap::new();
 
     for corpus in session.selected_corpora().iter() {
         if let Ok(corpus_segmentations) =
-            client::corpora::segmentations(&SessionArg::Session(session.clone()), corpus, &state)
+            client::corpora::segmentations(&SessionArg::Session(session.clone()), corpus, state)
                 .await
         {
             for seg in corpus_segmentations {
@@ -74,7 +139,7 @@ async fn show_page(
         }
     }
 
-    let segmentations: Vec<_> = all_segmentations
+    all_segmentations
         .into_iter()
         .filter_map(|(k, v)| {
             if v == number_collected_corpora {
@@ -83,21 +148,7 @@ async fn show_page(
                 None
             }
         })
-        .collect();
-
-    let result = state
-        .templates
-        .get_template("export.html")?
-        .render(context! {
-            example,
-            session => session,
-            job => current_job(&session, &state),
-            config => params.config,
-            default_context_sizes,
-            segmentations,
-        })?;
-
-    Ok(Html(result))
+        .collect()
 }
 
 async fn create_job(
@@ -107,32 +158,87 @@ async fn create_job(
 ) -> Result<impl IntoResponse> {
     // Only allow one background job per session
     let session_arg = SessionArg::Id(session.id().to_string());
-    app_state
-        .background_jobs
-        .entry(session_arg.id().to_string())
-        .or_insert_with(|| {
+    let permalink = params
+        .include_citation
+        .then(|| params.permalink_url(&session, &app_state));
+    let query = params.query.clone().unwrap_or_default();
+    let job_started = match app_state.background_jobs.entry(session_arg.id()) {
+        Entry::Occupied(_) => false,
+        Entry::Vacant(entry) => {
             // Create a background job that performs the export
             let find_query = FindQuery {
-                query: params.query.unwrap_or_default(),
+                query: query.clone(),
                 corpora: session.selected_corpora().iter().cloned().collect(),
-                query_language: QueryLanguage::AQL,
+                query_language: params.query_language,
                 limit: None,
+                offset: 0,
                 order: ResultOrder::Normal,
             };
-            let config = params.config;
+            let config = params.config.clone();
             let app_state_copy = app_state.clone();
             let (sender, receiver) = channel(1);
             let handle: JoinHandle<Result<NamedTempFile>> = tokio::spawn(async move {
-                let mut exporter = CSVExporter::new(find_query, config, Some(sender));
                 let mut result_file = tempfile::NamedTempFile::new()?;
+                if let Some(permalink) = permalink {
+                    let citation = Citation::create(
+                        &session_arg,
+                        &find_query.query,
+                        permalink,
+                        &find_query.corpora,
+                        &app_state_copy,
+                    )
+                    .await?;
+                    result_file.write_all(citation.header().as_bytes())?;
+                }
+                let mut exporter = CSVExporter::new(find_query, config, Some(sender));
 
                 exporter
                     .convert_text(session_arg, &app_state_copy, None, &mut result_file)
                     .await?;
                 Ok(result_file)
             });
-            ExportJob::new(handle, receiver)
-        });
+            entry.insert(ExportJob::new(handle, receiver));
+            true
+        }
+    };
+
+    if job_started {
+        // Counting the matches can take as long as the export itself, so the
+        // entry is recorded without the number of matches. It is added later
+        // to the history of a logged in user.
+        let entry = HistoryEntry::new(
+            &query,
+            params.query_language,
+            &params.config,
+            session.selected_corpora(),
+            None,
+        );
+        if let Err(e) = history::record(&session, entry.clone(), &app_state).await {
+            warn!("Could not record the export in the query history: {e}");
+        }
+        if let Ok(Some(user_id)) = app_state.user_id(&session) {
+            let session_arg = SessionArg::Id(session.id().to_string());
+            let app_state = app_state.clone();
+            tokio::spawn(async move {
+                let count_query = CountQuery {
+                    query: entry.query.clone(),
+                    corpora: entry.corpora.clone(),
+                    query_language: entry.query_language,
+                };
+                // Errors of the query are shown by the export job, so the
+                // number of matches just stays unknown in this case
+                if let Ok(count) = search::count(&session_arg, &count_query, &app_state).await {
+                    if let Err(e) = app_state
+                        .user_store
+                        .set_history_match_count(&user_id, &entry, count.match_count)
+                        .await
+                    {
+                        warn!("Could not update the query history: {e}");
+                    }
+                }
+            });
+        }
+    }
 
     // Only render the export job status template
     let result = app_state
@@ -202,21 +308,37 @@ async fn download_file(
     }
 }
 
-#[derive(Clone, Debug, Serialize)]
-enum JobState {
-    Idle,
-    Running(f32),
-    Finished,
+/// Show the permalink and the citation of the query and selected corpora.
+async fn show_citation(
+    session: Session,
+    Query(params): Query<FormParams>,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    let corpora: Vec<String> = session.selected_corpora().iter().cloned().collect();
+    let citation = Citation::create(
+        &SessionArg::Session(session.clone()),
+        params.query.as_deref().unwrap_or_default(),
+        params.permalink_url(&session, &app_state),
+        &corpora,
+        &app_state,
+    )
+    .await?;
+
+    let result = app_state
+        .templates
+        .get_template("export/citation.html")?
+        .render(context! {
+            bibtex => citation.bibtex(),
+            apa => citation.apa(),
+            citation,
+        })?;
+    Ok(Html(result))
 }
 
 fn current_job(session: &Session, app_state: &GlobalAppState) -> JobState {
     let session_id = session.id().to_string();
     if let Some(mut job) = app_state.background_jobs.get_mut(&session_id) {
-        if job.handle.is_finished() {
-            JobState::Finished
-        } else {
-            JobState::Running(job.get_progress())
-        }
+        job.state()
     } else {
         JobState::Idle
     }
@@ -231,8 +353,9 @@ async fn create_example_output(
     let example_query = FindQuery {
         query: query.to_string(),
         corpora: session.selected_corpora().iter().cloned().collect(),
-        query_language: QueryLanguage::AQL,
+        query_language: params.query_language,
         limit: None,
+        offset: 0,
         order: ResultOrder::NotSorted,
     };
     let config = params.config.clone();
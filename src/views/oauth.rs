@@ -1,8 +1,9 @@
 use crate::auth::LoginInfo;
 use crate::{
     auth::schedule_refresh_token,
+    client::corpora,
     errors::AppError,
-    state::{GlobalAppState, Session},
+    state::{GlobalAppState, Session, SessionArg},
     Result,
 };
 use axum::{
@@ -71,7 +72,7 @@ struct CallBackParams {
 }
 
 async fn login_callback(
-    session: Session,
+    mut session: Session,
     State(app_state): State<Arc<GlobalAppState>>,
     Query(params): Query<CallBackParams>,
 ) -> Result<impl IntoResponse> {
@@ -105,10 +106,25 @@ async fn login_callback(
                 session.expiration_time().map(|t| t.unix_timestamp()),
             )?;
 
+            let user_id = login_info.user_id()?;
             app_state
                 .login_info
                 .insert(session.id().to_string(), login_info);
 
+            // Restore the corpus selection of the user and keep the corpora
+            // that have been selected before logging in. Corpora that do not
+            // exist anymore or that the user has no access to are ignored.
+            if let Some(user_id) = user_id {
+                let available =
+                    corpora::list(&SessionArg::Session(session.clone()), &app_state).await?;
+                let mut selected_corpora = app_state.user_store.selected_corpora(&user_id).await?;
+                selected_corpora.extend(session.selected_corpora().iter().cloned());
+                selected_corpora.retain(|c| available.contains(c));
+                session
+                    .set_selected_corpora(selected_corpora, &app_state)
+                    .await?;
+            }
+
             let html = template.render(context! {
                 session => session,
             })?;
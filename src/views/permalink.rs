@@ -0,0 +1,132 @@
+use std::{collections::BTreeSet, sync::Arc};
+
+use axum::{
+    extract::{Query, RawQuery, State},
+    response::{IntoResponse, Redirect},
+    routing::get,
+    Router,
+};
+use graphannis::corpusstorage::QueryLanguage;
+use serde::Deserialize;
+use serde_with::{serde_as, NoneAsEmptyString};
+
+use crate::{
+    client::corpora,
+    converter::CSVConfig,
+    state::{GlobalAppState, Session, SessionArg},
+    Result,
+};
+
+pub fn create_routes() -> Result<Router<Arc<GlobalAppState>>> {
+    let result = Router::new().route("/", get(restore));
+    Ok(result)
+}
+
+/// Everything needed to reproduce a query result, including the corpus
+/// selection. This is encoded in the parameters of the permalink URL.
+#[serde_as]
+#[derive(Deserialize, Debug, Clone)]
+pub struct Permalink {
+    query: String,
+    /// Corpus names can contain commas, so each corpus is given as a separate
+    /// `corpora` parameter, which is parsed by [`corpora_param`].
+    #[serde(skip)]
+    corpora: Vec<String>,
+    #[serde(default)]
+    query_language: QueryLanguage,
+    #[serde(default)]
+    left_context: usize,
+    #[serde(default)]
+    right_context: usize,
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    segmentation: Option<String>,
+}
+
+impl Permalink {
+    pub fn new(
+        query: &str,
+        query_language: QueryLanguage,
+        config: &CSVConfig,
+        session: &Session,
+    ) -> Self {
+        Self {
+            query: query.to_string(),
+            corpora: session.selected_corpora().iter().cloned().collect(),
+            query_language,
+            left_context: config.left_context,
+            right_context: config.right_context,
+            segmentation: config.span_segmentation.clone(),
+        }
+    }
+
+    /// The URL of the permalink for the given URL prefix of the frontend.
+    pub fn url(&self, frontend_prefix: &str) -> String {
+        let mut params = url::form_urlencoded::Serializer::new(String::new());
+        for corpus in &self.corpora {
+            params.append_pair("corpora", corpus);
+        }
+        let params = params
+            .append_pair("left_context", &self.left_context.to_string())
+            .append_pair("query", &self.query)
+            .append_pair("query_language", query_language_name(self.query_language))
+            .append_pair("right_context", &self.right_context.to_string())
+            .append_pair(
+                "segmentation",
+                self.segmentation.as_deref().unwrap_or_default(),
+            )
+            .finish();
+        format!("{frontend_prefix}permalink?{params}")
+    }
+}
+
+/// All values of the repeated `corpora` parameter of the query string.
+fn corpora_param(query: Option<&str>) -> Vec<String> {
+    url::form_urlencoded::parse(query.unwrap_or_default().as_bytes())
+        .filter(|(key, _)| key == "corpora")
+        .map(|(_, corpus)| corpus.into_owned())
+        .collect()
+}
+
+pub(crate) fn query_language_name(query_language: QueryLanguage) -> &'static str {
+    match query_language {
+        QueryLanguage::AQL => "AQL",
+        QueryLanguage::AQLQuirksV3 => "AQLQuirksV3",
+    }
+}
+
+/// Select the corpora of the permalink and show the query on the export page.
+/// Corpora that do not exist or that the user has no access to are ignored.
+async fn restore(
+    mut session: Session,
+    Query(permalink): Query<Permalink>,
+    RawQuery(raw_query): RawQuery,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    let available = corpora::list(&SessionArg::Session(session.clone()), &app_state).await?;
+    let selected_corpora: BTreeSet<String> = corpora_param(raw_query.as_deref())
+        .into_iter()
+        .filter(|c| available.contains(c))
+        .collect();
+    session
+        .set_selected_corpora(selected_corpora, &app_state)
+        .await?;
+
+    let params = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("query", &permalink.query)
+        .append_pair(
+            "query_language",
+            query_language_name(permalink.query_language),
+        )
+        .append_pair("left_context", &permalink.left_context.to_string())
+        .append_pair("right_context", &permalink.right_context.to_string())
+        .append_pair(
+            "span_segmentation",
+            permalink.segmentation.as_deref().unwrap_or_default(),
+        )
+        .finish();
+    Ok(Redirect::temporary(&format!("export?{params}")))
+}
+
+#[cfg(test)]
+mod tests;
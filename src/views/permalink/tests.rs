@@ -0,0 +1,182 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{header::LOCATION, Request, StatusCode},
+};
+use mockito::{Matcher, Server};
+use scraper::Selector;
+use test_log::test;
+use tower::ServiceExt;
+
+use crate::{
+    config::CliConfig,
+    state::GlobalAppState,
+    tests::{create_session_with_corpora, get_html},
+};
+
+#[test(tokio::test)]
+async fn permalink_restores_corpus_selection() {
+    let mut service_mock = Server::new_with_port(0);
+    service_mock
+        .mock("GET", "/corpora")
+        .with_header("content-type", "application/json")
+        .with_body(r#"["RIDGES", "news, 2023", "pcc2"]"#)
+        .create();
+
+    let config = CliConfig {
+        service_url: service_mock.url(),
+        ..Default::default()
+    };
+    let (session_cookie, session_store) = create_session_with_corpora(&["RIDGES"]).await;
+    let app_state = Arc::new(GlobalAppState::new(&config).unwrap());
+    let app = crate::app_with_state(app_state, session_store, chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+
+    // Unknown corpora are not selected and corpus names can contain commas
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/permalink?query=tok%20%3D%20%22a%22&corpora=pcc2&corpora=news%2C+2023&corpora=unknown&query_language=AQLQuirksV3&left_context=5&right_context=3&segmentation=dipl")
+                .header("Cookie", session_cookie.clone())
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(
+        "export?query=tok+%3D+%22a%22&query_language=AQLQuirksV3&left_context=5&right_context=3&span_segmentation=dipl",
+        response.headers()[LOCATION]
+    );
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/about")
+                .header("Cookie", session_cookie)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let html = get_html(response).await;
+    let selected = html
+        .select(&Selector::parse("#annis-corpora-navbar-item span").unwrap())
+        .next()
+        .map(|e| {
+            (
+                e.inner_html(),
+                e.value().attr("title").unwrap_or_default().to_string(),
+            )
+        });
+    assert_eq!(
+        Some(("2".to_string(), "news, 2023, pcc2".to_string())),
+        selected
+    );
+}
+
+#[test(tokio::test)]
+async fn export_page_shows_permalink() {
+    let service_mock = Server::new_with_port(0);
+    let config = CliConfig {
+        service_url: service_mock.url(),
+        ..Default::default()
+    };
+    let (session_cookie, session_store) = create_session_with_corpora(&["pcc2"]).await;
+    let app_state = Arc::new(GlobalAppState::new(&config).unwrap());
+    let app = crate::app_with_state(app_state, session_store, chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/export?query=tok&query_language=AQLQuirksV3&left_context=5&right_context=3&span_segmentation=")
+                .header("Cookie", session_cookie)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = get_html(response).await;
+
+    let permalink = html
+        .select(&Selector::parse("#permalink input").unwrap())
+        .next()
+        .and_then(|e| e.value().attr("value"))
+        .map(str::to_string);
+    assert_eq!(
+        Some("http://127.0.0.1:3000/permalink?corpora=pcc2&left_context=5&query=tok&query_language=AQLQuirksV3&right_context=3&segmentation=".to_string()),
+        permalink
+    );
+    // The state of the permalink is restored in the form
+    let selected: Vec<_> = html
+        .select(&Selector::parse("select option[selected]").unwrap())
+        .map(|o| o.value().attr("value").unwrap_or_default().to_string())
+        .collect();
+    assert_eq!(vec!["AQLQuirksV3", "5", "3"], selected);
+    let query = html
+        .select(&Selector::parse("#aql-input").unwrap())
+        .next()
+        .map(|e| e.inner_html());
+    assert_eq!(Some("tok".to_string()), query);
+}
+
+#[test(tokio::test)]
+async fn citation_uses_corpus_metadata() {
+    let mut service_mock = Server::new_with_port(0);
+    service_mock
+        .mock("GET", "/corpora/pcc2/subgraph-for-query")
+        .match_query(Matcher::UrlEncoded(
+            "query".into(),
+            "annis:node_type=\"corpus\"".into(),
+        ))
+        .with_body_from_file("tests/pcc2-corpus-graph.graphml")
+        .create();
+    let config = CliConfig {
+        service_url: service_mock.url(),
+        ..Default::default()
+    };
+    let (session_cookie, session_store) = create_session_with_corpora(&["pcc2"]).await;
+    let app_state = Arc::new(GlobalAppState::new(&config).unwrap());
+    let app = crate::app_with_state(app_state, session_store, chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/export/citation?query=tok&left_context=5&right_context=5&span_segmentation=")
+                .header("Cookie", session_cookie)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = get_html(response).await;
+
+    let apa = html
+        .select(&Selector::parse("#citation-apa").unwrap())
+        .next()
+        .map(|e| e.inner_html())
+        .unwrap_or_default();
+    assert!(apa.starts_with(
+        "Potsdam Commentary Corpus (sample of 2 documents) (Version 7.0) [Data set]. (n.d.).\nANNIS query \"tok\", retrieved "
+    ));
+    assert!(apa.ends_with(
+        " from http://127.0.0.1:3000/permalink?corpora=pcc2&amp;left_context=5&amp;query=tok&amp;query_language=AQL&amp;right_context=5&amp;segmentation="
+    ));
+    let bibtex = html
+        .select(&Selector::parse("#citation-bibtex").unwrap())
+        .next()
+        .map(|e| e.inner_html())
+        .unwrap_or_default();
+    assert!(bibtex.starts_with(
+        "@misc{pcc2,\n  title = {Potsdam Commentary Corpus (sample of 2 documents)},\n  version = {7.0},\n"
+    ));
+}
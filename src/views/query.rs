@@ -0,0 +1,420 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    sync::Arc,
+};
+
+use axum::{
+    extract::{Query, State},
+    response::{Html, IntoResponse},
+    routing::get,
+    Router,
+};
+use graphannis::{corpusstorage::QueryLanguage, graph::Annotation};
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::{
+        corpora::{self, ExampleQuery},
+        search::{self, QueryNodeDescription},
+    },
+    errors::{AppError, LineColumn},
+    history,
+    state::{GlobalAppState, Session, SessionArg},
+    Result,
+};
+
+pub fn create_routes() -> Result<Router<Arc<GlobalAppState>>> {
+    let result = Router::new()
+        .route("/validate", get(validate))
+        .route("/suggestions", get(suggestions))
+        .route("/examples", get(examples))
+        .route("/history", get(history));
+    Ok(result)
+}
+
+#[derive(Deserialize, Debug)]
+struct QueryParams {
+    query: Option<String>,
+    #[serde(default)]
+    query_language: QueryLanguage,
+}
+
+/// An error message for a query, with the query text split up so the part
+/// that caused the error can be highlighted.
+#[derive(Serialize, Debug)]
+struct QueryError {
+    message: String,
+    before: String,
+    highlighted: String,
+    after: String,
+}
+
+/// A node of the query as shown in the node description panel.
+#[derive(Serialize, Debug)]
+struct QueryNode {
+    #[serde(flatten)]
+    description: QueryNodeDescription,
+    /// Position of this node in a match, which is used as suffix for the
+    /// column names in the CSV export. Optional nodes have no fixed position.
+    match_position: Option<usize>,
+}
+
+async fn validate(
+    session: Session,
+    Query(params): Query<QueryParams>,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    let query = params.query.unwrap_or_default();
+
+    let mut nodes = Vec::new();
+    let mut number_of_alternatives = 0;
+
+    let query_error = if query.trim().is_empty() {
+        None
+    } else {
+        match search::node_descriptions(
+            &SessionArg::Session(session.clone()),
+            &query,
+            params.query_language,
+            &app_state,
+        )
+        .await
+        {
+            Ok(descriptions) => {
+                // Non-optional nodes are numbered in the order of their
+                // appearance in each alternative
+                let mut next_position: HashMap<usize, usize> = HashMap::new();
+                for description in descriptions {
+                    let match_position = if description.optional {
+                        None
+                    } else {
+                        let position = next_position.entry(description.alternative).or_insert(0);
+                        *position += 1;
+                        Some(*position)
+                    };
+                    nodes.push(QueryNode {
+                        description,
+                        match_position,
+                    });
+                }
+                number_of_alternatives = nodes
+                    .iter()
+                    .map(|n| n.description.alternative)
+                    .collect::<HashSet<_>>()
+                    .len();
+                None
+            }
+            Err(AppError::BackendBadRequest(e)) => {
+                let chars: Vec<char> = query.chars().collect();
+                let (start, end) = if let Some(location) = e.query_location() {
+                    let start = char_index(&chars, &location.start);
+                    // The end position is inclusive
+                    let end = location
+                        .end
+                        .as_ref()
+                        .map(|end| char_index(&chars, end) + 1)
+                        .unwrap_or(start + 1)
+                        .clamp(start, chars.len());
+                    (start, end)
+                } else {
+                    (chars.len(), chars.len())
+                };
+                Some(QueryError {
+                    message: e.to_string(),
+                    before: chars[..start].iter().collect(),
+                    highlighted: chars[start..end].iter().collect(),
+                    after: chars[end..].iter().collect(),
+                })
+            }
+            Err(e) => return Err(e),
+        }
+    };
+
+    let html = app_state
+        .templates
+        .get_template("query/validation.html")?
+        .render(context! {
+            query_error,
+            nodes,
+            number_of_alternatives,
+        })?;
+
+    Ok(Html(html))
+}
+
+/// Maximum number of suggestions shown at once.
+const MAX_SUGGESTIONS: usize = 10;
+
+#[derive(Deserialize, Debug)]
+struct SuggestionParams {
+    query: Option<String>,
+    /// Position of the cursor in the query, given in UTF-16 code units as
+    /// reported by the browser.
+    cursor: Option<usize>,
+}
+
+#[derive(Serialize, Debug)]
+struct Suggestion {
+    /// Text that replaces the already typed prefix.
+    text: String,
+}
+
+/// What is currently typed at the cursor position.
+#[derive(Debug, PartialEq)]
+enum CompletionContext<'a> {
+    AnnoKey { prefix: &'a str },
+    AnnoValue { key: &'a str, prefix: &'a str },
+}
+
+async fn suggestions(
+    session: Session,
+    Query(params): Query<SuggestionParams>,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    let query = params.query.unwrap_or_default();
+    let cursor = byte_offset_from_utf16(&query, params.cursor.unwrap_or(query.len()));
+    let (before_cursor, after_cursor) = query.split_at(cursor);
+
+    let mut suggestions = Vec::new();
+    let mut prefix = "";
+
+    if !session.selected_corpora().is_empty() {
+        match completion_context(before_cursor) {
+            Some(CompletionContext::AnnoKey { prefix: key_prefix }) => {
+                prefix = key_prefix;
+                let key_prefix = key_prefix.to_lowercase();
+                let keys: BTreeSet<_> = cached_node_annotations(&session, false, &app_state)
+                    .await?
+                    .into_iter()
+                    .filter(|anno| anno.key.ns != "annis")
+                    .map(|anno| {
+                        (
+                            graphannis_core::util::join_qname(&anno.key.ns, &anno.key.name),
+                            anno.key.name,
+                        )
+                    })
+                    .collect();
+                suggestions.extend(
+                    keys.into_iter()
+                        .filter(|(qname, name)| {
+                            qname.to_lowercase().starts_with(&key_prefix)
+                                || name.to_lowercase().starts_with(&key_prefix)
+                        })
+                        .filter(|(qname, _)| qname != prefix)
+                        .take(MAX_SUGGESTIONS)
+                        .map(|(qname, _)| Suggestion { text: qname }),
+                );
+            }
+            Some(CompletionContext::AnnoValue {
+                key,
+                prefix: value_prefix,
+            }) => {
+                prefix = value_prefix;
+                let (ns, name) = graphannis_core::util::split_qname(key);
+                // Also close the string literal, unless it already is
+                let closing_quote = if after_cursor.starts_with('"') {
+                    ""
+                } else {
+                    "\""
+                };
+                let value_prefix = value_prefix.to_lowercase();
+                let values: BTreeSet<_> = cached_node_annotations(&session, true, &app_state)
+                    .await?
+                    .into_iter()
+                    .filter(|anno| {
+                        anno.key.name == name
+                            && ns.is_none_or(|ns| anno.key.ns == ns)
+                            && anno.val.to_lowercase().starts_with(&value_prefix)
+                    })
+                    .map(|anno| anno.val.to_string())
+                    .collect();
+                suggestions.extend(values.into_iter().take(MAX_SUGGESTIONS).map(|value| {
+                    Suggestion {
+                        text: format!("{}{closing_quote}", value.replace('"', "\\\"")),
+                    }
+                }));
+            }
+            None => {}
+        }
+    }
+
+    // The browser expects positions in UTF-16 code units
+    let replace_end: usize = before_cursor.encode_utf16().count();
+    let replace_start = replace_end - prefix.encode_utf16().count();
+
+    let html = app_state
+        .templates
+        .get_template("query/suggestions.html")?
+        .render(context! {
+            suggestions,
+            replace_start,
+            replace_end,
+        })?;
+
+    Ok(Html(html))
+}
+
+/// The node annotations of all given corpora. If `with_values` is true, the
+/// most frequent value of each annotation is included. The result is cached
+/// for each user and set of corpora, so typing a query does not query the
+/// backend on every key stroke. Anonymous sessions share the cache, since
+/// they all have the same access rights.
+async fn cached_node_annotations(
+    session: &Session,
+    with_values: bool,
+    app_state: &GlobalAppState,
+) -> Result<Vec<Annotation>> {
+    let corpora = session.selected_corpora();
+    let cache_key = (app_state.user_id(session)?, corpora.clone(), with_values);
+    if let Some(annotations) = app_state.node_annotations.get(&cache_key) {
+        return Ok(annotations.clone());
+    }
+    let session_arg = SessionArg::Session(session.clone());
+    let mut annotations = Vec::new();
+    for corpus in corpora {
+        annotations.extend(
+            corpora::node_annotations(&session_arg, corpus, with_values, with_values, app_state)
+                .await?,
+        );
+    }
+    app_state
+        .node_annotations
+        .insert(cache_key, annotations.clone());
+    Ok(annotations)
+}
+
+fn is_qname_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == ':'
+}
+
+/// Determine whether an annotation name or value is typed at the end of the
+/// given text.
+fn completion_context(before_cursor: &str) -> Option<CompletionContext<'_>> {
+    let number_of_quotes = before_cursor.chars().filter(|c| *c == '"').count();
+    if number_of_quotes % 2 == 1 {
+        // The cursor is inside a string literal, which could be the value of an annotation
+        let (before_value, prefix) = before_cursor.rsplit_once('"')?;
+        let before_value = before_value.trim_end();
+        let before_value = before_value
+            .strip_suffix("!=")
+            .or_else(|| before_value.strip_suffix('='))?
+            .trim_end();
+        let key_start = before_value
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| is_qname_char(*c))
+            .last()?
+            .0;
+        Some(CompletionContext::AnnoValue {
+            key: &before_value[key_start..],
+            prefix,
+        })
+    } else {
+        let prefix_start = before_cursor
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| is_qname_char(*c))
+            .last()?
+            .0;
+        let prefix = &before_cursor[prefix_start..];
+        let is_variable_reference = before_cursor[..prefix_start].ends_with('#');
+        if is_variable_reference || prefix.chars().all(|c| c.is_ascii_digit()) {
+            None
+        } else {
+            Some(CompletionContext::AnnoKey { prefix })
+        }
+    }
+}
+
+/// Converts a position given in UTF-16 code units to a byte offset in the
+/// string. Positions after the end are mapped to the length of the string.
+fn byte_offset_from_utf16(text: &str, position: usize) -> usize {
+    let mut utf16_position = 0;
+    for (offset, c) in text.char_indices() {
+        if utf16_position >= position {
+            return offset;
+        }
+        utf16_position += c.len_utf16();
+    }
+    text.len()
+}
+
+#[derive(Serialize, Debug)]
+struct CorpusExampleQuery {
+    corpus: String,
+    #[serde(flatten)]
+    example: ExampleQuery,
+}
+
+async fn examples(
+    session: Session,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    let session_arg = SessionArg::Session(session.clone());
+    let mut examples = Vec::new();
+    for corpus in session.selected_corpora() {
+        let config = corpora::configuration(&session_arg, corpus, &app_state).await?;
+        examples.extend(
+            config
+                .example_queries
+                .into_iter()
+                .map(|example| CorpusExampleQuery {
+                    corpus: corpus.clone(),
+                    example,
+                }),
+        );
+    }
+
+    let html = app_state
+        .templates
+        .get_template("query/examples.html")?
+        .render(context! {
+            examples,
+        })?;
+
+    Ok(Html(html))
+}
+
+/// Show the executed queries of the session or the logged in user.
+async fn history(
+    session: Session,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    let entries = history::entries(&session, &app_state).await?;
+
+    let html = app_state
+        .templates
+        .get_template("query/history.html")?
+        .render(context! {
+            entries,
+        })?;
+
+    Ok(Html(html))
+}
+
+/// Maps a 1-based line and column position to the index of the character in
+/// the query. Positions after the end of the query are mapped to its length.
+fn char_index(chars: &[char], position: &LineColumn) -> usize {
+    let mut line = 1;
+    let mut column = 1;
+    for (idx, c) in chars.iter().enumerate() {
+        if line == position.line && column == position.column {
+            return idx;
+        }
+        if *c == '\n' {
+            if line == position.line {
+                // The column is after the end of the line
+                return idx;
+            }
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    chars.len()
+}
+
+#[cfg(test)]
+mod tests;
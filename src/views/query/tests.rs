@@ -0,0 +1,565 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use mockito::{Matcher, Server};
+use scraper::{Html, Selector};
+use test_log::test;
+use tower::ServiceExt;
+
+use super::{completion_context, CompletionContext};
+use crate::{
+    config::CliConfig,
+    state::GlobalAppState,
+    tests::{add_session, create_session_with_corpora, get_html, login_info},
+};
+
+#[test(tokio::test)]
+async fn validate_highlights_error() {
+    let mut service_mock = Server::new_with_port(0);
+    let m = service_mock
+        .mock("GET", "/search/node-descriptions")
+        .match_query(Matcher::UrlEncoded("query".into(), "pos &\npos".into()))
+        .with_status(400)
+        .with_header("content-type", "application/json")
+        .with_body(
+            r##"{
+                "AQLSemanticError": {
+                    "desc": "Variable \"#2\" not bound (use linguistic operators)",
+                    "location": {
+                        "start": {
+                            "line": 2,
+                            "column": 1
+                        },
+                        "end": {
+                            "line": 2,
+                            "column": 3
+                        }
+                    }
+                }
+            }"##,
+        )
+        .create();
+
+    let config = CliConfig {
+        service_url: service_mock.url(),
+        ..Default::default()
+    };
+    let app = crate::app(&config, chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/query/validate?query=pos%20%26%0Apos")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = get_html(response).await;
+
+    let highlighted: Vec<_> = html
+        .select(&Selector::parse("#aql-validation pre u").unwrap())
+        .collect();
+    assert_eq!(1, highlighted.len());
+    assert_eq!("pos", highlighted[0].inner_html());
+
+    let pre: Vec<_> = html
+        .select(&Selector::parse("#aql-validation pre").unwrap())
+        .collect();
+    assert_eq!("pos &\npos", pre[0].text().collect::<String>());
+
+    let message: Vec<_> = html
+        .select(&Selector::parse("#aql-validation p.is-danger").unwrap())
+        .collect();
+    assert_eq!(
+        "Semantic error in query:\n[2:1-2:3] Variable \"#2\" not bound (use linguistic operators)",
+        message[0].text().collect::<String>()
+    );
+
+    m.assert();
+}
+
+#[test(tokio::test)]
+async fn validate_shows_query_nodes() {
+    let mut service_mock = Server::new_with_port(0);
+    let m = service_mock
+        .mock("GET", "/search/node-descriptions")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("query".into(), "pos=\"ART\" . tok ?".into()),
+            Matcher::UrlEncoded("query_language".into(), "AQLQuirksV3".into()),
+        ]))
+        .with_header("content-type", "application/json")
+        .with_body(
+            r#"[
+                {"alternative": 0, "query_fragment": "pos=\"ART\"", "variable": "1", "anno_name": "pos", "optional": false},
+                {"alternative": 0, "query_fragment": "tok", "variable": "2", "anno_name": null, "optional": true}
+            ]"#,
+        )
+        .create();
+
+    let config = CliConfig {
+        service_url: service_mock.url(),
+        ..Default::default()
+    };
+    let app = crate::app(&config, chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/query/validate?query=pos%3D%22ART%22%20.%20tok%20%3F&query_language=AQLQuirksV3")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = get_html(response).await;
+    let errors: Vec<_> = html
+        .select(&Selector::parse("#aql-validation pre").unwrap())
+        .collect();
+    assert_eq!(0, errors.len());
+
+    let rows: Vec<Vec<String>> = html
+        .select(&Selector::parse("#query-nodes tbody tr").unwrap())
+        .map(|row| {
+            row.select(&Selector::parse("td").unwrap())
+                .map(|cell| cell.text().collect::<String>().trim().to_string())
+                .collect()
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            vec!["#1", "pos", "pos=\"ART\"", "no", "(1)"],
+            vec!["#2", "", "tok", "yes", ""]
+        ],
+        rows
+    );
+
+    m.assert();
+}
+
+#[test]
+fn detect_completion_context() {
+    assert_eq!(
+        Some(CompletionContext::AnnoKey { prefix: "tiger::p" }),
+        completion_context("tok . tiger::p")
+    );
+    assert_eq!(
+        Some(CompletionContext::AnnoValue {
+            key: "tiger::pos",
+            prefix: "AR"
+        }),
+        completion_context("tok . tiger::pos=\"AR")
+    );
+    assert_eq!(
+        Some(CompletionContext::AnnoValue {
+            key: "pos",
+            prefix: ""
+        }),
+        completion_context("pos != \"")
+    );
+    assert_eq!(None, completion_context("pos=\"ART\" "));
+    assert_eq!(None, completion_context("pos=\"ART\" & tok & #1 . #2"));
+    assert_eq!(None, completion_context(""));
+}
+
+#[test(tokio::test)]
+async fn suggest_annotation_keys() {
+    let mut service_mock = Server::new_with_port(0);
+    let m = service_mock
+        .mock("GET", "/corpora/pcc2/node-annotations")
+        .match_query(Matcher::UrlEncoded("list_values".into(), "false".into()))
+        .with_header("content-type", "application/json")
+        .with_body(
+            r#"[
+                {"key": {"ns": "annis", "name": "tok"}, "val": ""},
+                {"key": {"ns": "tiger", "name": "lemma"}, "val": ""},
+                {"key": {"ns": "tiger", "name": "pos"}, "val": ""},
+                {"key": {"ns": "exmaralda", "name": "Inf-Stat"}, "val": ""},
+                {"key": {"ns": "", "name": "pos_lang"}, "val": ""}
+            ]"#,
+        )
+        .create();
+
+    let config = CliConfig {
+        service_url: service_mock.url(),
+        ..Default::default()
+    };
+    let (session_cookie, session_store) = create_session_with_corpora(&["pcc2"]).await;
+    let app_state = Arc::new(GlobalAppState::new(&config).unwrap());
+    let app = crate::app_with_state(app_state, session_store, chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/query/suggestions?query=tok%20.%20po&cursor=8")
+                .header("Cookie", session_cookie)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = get_html(response).await;
+    let items: Vec<_> = html
+        .select(&Selector::parse("#aql-suggestions a.dropdown-item").unwrap())
+        .map(|item| {
+            item.value()
+                .attr("data-text")
+                .unwrap_or_default()
+                .to_string()
+        })
+        .collect();
+    assert_eq!(vec!["pos_lang", "tiger::pos"], items);
+
+    m.assert();
+}
+
+#[test(tokio::test)]
+async fn suggest_annotation_values() {
+    let mut service_mock = Server::new_with_port(0);
+    let m = service_mock
+        .mock("GET", "/corpora/pcc2/node-annotations")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("list_values".into(), "true".into()),
+            Matcher::UrlEncoded("only_most_frequent_values".into(), "true".into()),
+        ]))
+        .with_header("content-type", "application/json")
+        .with_body(
+            r#"[
+                {"key": {"ns": "tiger", "name": "pos"}, "val": "ART"},
+                {"key": {"ns": "", "name": "pos"}, "val": "ADV"},
+                {"key": {"ns": "tiger", "name": "lemma"}, "val": "also"},
+                {"key": {"ns": "", "name": "pos"}, "val": "NN"}
+            ]"#,
+        )
+        // The annotations are only fetched once for each set of corpora
+        .expect(1)
+        .create();
+
+    let config = CliConfig {
+        service_url: service_mock.url(),
+        ..Default::default()
+    };
+    let (session_cookie, session_store) = create_session_with_corpora(&["pcc2"]).await;
+    let app_state = Arc::new(GlobalAppState::new(&config).unwrap());
+    let app = crate::app_with_state(app_state, session_store, chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+
+    for (query, expected) in [
+        ("pos%3D%22a", vec!["ADV\"", "ART\""]),
+        ("pos%3D%22n", vec!["NN\""]),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/query/suggestions?query={query}&cursor=6"))
+                    .header("Cookie", &session_cookie)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let html = get_html(response).await;
+        let items: Vec<_> = html
+            .select(&Selector::parse("#aql-suggestions a.dropdown-item").unwrap())
+            .map(|item| {
+                item.value()
+                    .attr("data-text")
+                    .unwrap_or_default()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(expected, items);
+    }
+
+    m.assert();
+}
+
+#[test(tokio::test)]
+async fn suggestions_are_not_shared_with_sessions_without_access() {
+    let mut service_mock = Server::new_with_port(0);
+    let authorized = service_mock
+        .mock("GET", "/corpora/restricted/node-annotations")
+        .match_query(Matcher::Any)
+        .match_header("authorization", Matcher::Regex("^Bearer ".into()))
+        .with_header("content-type", "application/json")
+        .with_body(r#"[{"key": {"ns": "secret", "name": "pos"}, "val": ""}]"#)
+        .expect(1)
+        .create();
+    let anonymous = service_mock
+        .mock("GET", "/corpora/restricted/node-annotations")
+        .match_query(Matcher::Any)
+        .match_header("authorization", Matcher::Missing)
+        .with_status(403)
+        .expect(1)
+        .create();
+    service_mock
+        .mock("GET", "/corpora")
+        .with_header("content-type", "application/json")
+        .with_body(r#"[]"#)
+        .create();
+
+    let config = CliConfig {
+        service_url: service_mock.url(),
+        ..Default::default()
+    };
+    let (user_cookie, session_store) = create_session_with_corpora(&["restricted"]).await;
+    let user_id = user_cookie.trim_start_matches("tower.sid=").to_string();
+    let (_, anonymous_cookie) = add_session(&session_store).await;
+    let app_state = Arc::new(GlobalAppState::new(&config).unwrap());
+    app_state.login_info.insert(user_id, login_info("testuser"));
+    let app = crate::app_with_state(app_state, session_store, chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+
+    let suggest = |cookie: String| {
+        let app = app.clone();
+        async move {
+            app.oneshot(
+                Request::builder()
+                    .uri("/query/suggestions?query=po&cursor=2")
+                    .header("Cookie", cookie)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+        }
+    };
+
+    let response = suggest(user_cookie).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = get_html(response).await;
+    assert_eq!(
+        1,
+        html.select(&Selector::parse("#aql-suggestions a.dropdown-item").unwrap())
+            .count()
+    );
+
+    // The anonymous session selects the same corpus, but the annotations
+    // are not taken from the cache of the user
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/corpora")
+                .header("Cookie", &anonymous_cookie)
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body(Body::from("filter=&add_corpus=restricted"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = suggest(anonymous_cookie).await;
+    assert_ne!(response.status(), StatusCode::OK);
+
+    authorized.assert();
+    anonymous.assert();
+}
+
+#[test(tokio::test)]
+async fn list_example_queries() {
+    let mut service_mock = Server::new_with_port(0);
+    let m = service_mock
+        .mock("GET", "/corpora/pcc2/configuration")
+        .with_header("content-type", "application/json")
+        .with_body(
+            r#"{
+                "context": {"default": 5, "sizes": [0, 1, 2, 5, 10], "max": null, "segmentation": null},
+                "view": {"base_text_segmentation": null, "page_size": 10},
+                "example_queries": [
+                    {
+                        "query": "Inf-Stat=\"new\" _o_ pos=\"PPER\"",
+                        "description": "information status new and personal pronoun",
+                        "query_language": "AQL"
+                    },
+                    {
+                        "query": "cat=\"S\" >* tok",
+                        "description": "sentences",
+                        "query_language": "AQLQuirksV3"
+                    }
+                ]
+            }"#,
+        )
+        .create();
+
+    let config = CliConfig {
+        service_url: service_mock.url(),
+        ..Default::default()
+    };
+    let (session_cookie, session_store) = create_session_with_corpora(&["pcc2"]).await;
+    let app_state = Arc::new(GlobalAppState::new(&config).unwrap());
+    let app = crate::app_with_state(app_state, session_store, chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/query/examples")
+                .header("Cookie", session_cookie)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = get_html(response).await;
+    let buttons: Vec<_> = html
+        .select(&Selector::parse("#example-queries button").unwrap())
+        .map(|b| b.value().attr("data-query").unwrap_or_default().to_string())
+        .collect();
+    assert_eq!(
+        vec!["Inf-Stat=\"new\" _o_ pos=\"PPER\"", "cat=\"S\" >* tok"],
+        buttons
+    );
+    let languages: Vec<_> = html
+        .select(&Selector::parse("#example-queries button").unwrap())
+        .map(|b| {
+            b.value()
+                .attr("data-query-language")
+                .unwrap_or_default()
+                .to_string()
+        })
+        .collect();
+    assert_eq!(vec!["AQL", "AQLQuirksV3"], languages);
+    let descriptions: Vec<_> = html
+        .select(&Selector::parse("#example-queries tbody td:nth-child(3)").unwrap())
+        .map(|td| td.inner_html())
+        .collect();
+    assert_eq!(
+        vec!["information status new and personal pronoun", "sentences"],
+        descriptions
+    );
+
+    m.assert();
+}
+
+#[test(tokio::test)]
+async fn history_is_shared_between_sessions_of_a_user() {
+    let mut service_mock = Server::new_with_port(0);
+    service_mock
+        .mock("POST", "/search/find")
+        .with_header("content-type", "text/plain")
+        .with_body("corefcorpus/doc1#t2\ncorefcorpus/doc1#t3\ncorefcorpus/doc1#t6\n")
+        .create();
+    service_mock
+        .mock("POST", "/corpora/corefcorpus/subgraph")
+        .with_body_from_file("tests/coreference-document.graphml")
+        .create();
+    service_mock
+        .mock("POST", "/search/count")
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"match_count": 3, "document_count": 1}"#)
+        .create();
+    let config = CliConfig {
+        service_url: service_mock.url(),
+        ..Default::default()
+    };
+
+    // Create a second and a third session in the same store, the first two
+    // sessions belong to the same user
+    let (first_cookie, session_store) = create_session_with_corpora(&["corefcorpus"]).await;
+    let first_id = first_cookie.trim_start_matches("tower.sid=").to_string();
+    let (second_id, second_cookie) = add_session(&session_store).await;
+    let (_, third_cookie) = add_session(&session_store).await;
+    let cookies = [first_cookie, second_cookie, third_cookie];
+    let app_state = Arc::new(GlobalAppState::new(&config).unwrap());
+    for session_id in [first_id, second_id] {
+        app_state
+            .login_info
+            .insert(session_id, login_info("testuser"));
+    }
+    let app = crate::app_with_state(app_state, session_store, chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+
+    let get = |uri: &'static str, cookie: String| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .uri(uri)
+                        .header("Cookie", cookie)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            get_html(response).await
+        }
+    };
+    let history = |html: &Html| -> Vec<String> {
+        html.select(&Selector::parse("#query-history tbody tr").unwrap())
+            .map(|tr| {
+                tr.select(&Selector::parse("td").unwrap())
+                    .skip(1)
+                    .take(3)
+                    .map(|td| td.text().collect::<String>().trim().to_string())
+                    .collect::<Vec<_>>()
+                    .join("|")
+            })
+            .collect()
+    };
+
+    get(
+        "/search/concordance?query=tok&left_context=3&right_context=2",
+        cookies[0].clone(),
+    )
+    .await;
+    // Showing another page does not add the query again
+    get(
+        "/search/concordance?query=tok&left_context=3&right_context=2&offset=10",
+        cookies[0].clone(),
+    )
+    .await;
+
+    let html = get("/query/history", cookies[0].clone()).await;
+    assert_eq!(vec!["tok|corefcorpus|3"], history(&html));
+    let html = get("/query/history", cookies[1].clone()).await;
+    assert_eq!(vec!["tok|corefcorpus|3"], history(&html));
+    let rerun = html
+        .select(&Selector::parse("#query-history a").unwrap())
+        .next()
+        .and_then(|a| a.value().attr("href"))
+        .map(str::to_string);
+    assert_eq!(
+        Some(
+            "http://127.0.0.1:3000/permalink?corpora=corefcorpus&left_context=3&query=tok&query_language=AQL&right_context=2&segmentation="
+                .to_string()
+        ),
+        rerun
+    );
+    let executed = html
+        .select(&Selector::parse("#query-history tbody td").unwrap())
+        .nth(4)
+        .map(|td| td.text().collect::<String>());
+    assert!(executed.is_some_and(|e| e.ends_with(" UTC")));
+
+    // Anonymous sessions have their own history
+    let html = get("/query/history", cookies[2].clone()).await;
+    assert!(history(&html).is_empty());
+}
@@ -0,0 +1,284 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use axum::{
+    extract::{Path, Query, State},
+    response::{Html, IntoResponse, Redirect},
+    routing::{get, post},
+    Form, Router,
+};
+use graphannis::corpusstorage::QueryLanguage;
+use minijinja::context;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Deserialize;
+use serde_with::{formats::Separator, serde_as, NoneAsEmptyString, StringWithSeparator};
+
+use crate::{
+    errors::AppError,
+    state::{GlobalAppState, Session},
+    user_store::SavedQuery,
+    Result,
+};
+
+pub fn create_routes() -> Result<Router<Arc<GlobalAppState>>> {
+    let result = Router::new()
+        .route("/", get(list))
+        .route("/", post(create))
+        .route("/new", get(new_query))
+        .route("/:id", get(show))
+        .route("/:id", post(update))
+        .route("/:id/delete", post(delete));
+    Ok(result)
+}
+
+fn logged_in_user(session: &Session, app_state: &GlobalAppState) -> Result<String> {
+    app_state.user_id(session)?.ok_or(AppError::LoginRequired)
+}
+
+/// Get the saved query and make sure the logged in user owns it.
+async fn owned_query(
+    id: &str,
+    session: &Session,
+    app_state: &GlobalAppState,
+) -> Result<SavedQuery> {
+    let user_id = logged_in_user(session, app_state)?;
+    let saved_query = app_state
+        .user_store
+        .saved_query(id)
+        .await?
+        .ok_or_else(|| AppError::SavedQueryNotFound(id.to_string()))?;
+    if saved_query.user_id == user_id {
+        Ok(saved_query)
+    } else {
+        Err(AppError::NotOwner)
+    }
+}
+
+/// Length of the random ID of a saved query.
+const ID_LENGTH: usize = 24;
+
+fn redirect_to_query(id: &str, app_state: &GlobalAppState) -> Redirect {
+    Redirect::to(&format!("{}saved/{id}", app_state.frontend_prefix))
+}
+
+/// The saved queries of the logged in user, grouped by their folder.
+async fn list(
+    session: Session,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    let mut folders: BTreeMap<String, Vec<SavedQuery>> = BTreeMap::new();
+    let user_id = app_state.user_id(&session)?;
+    if let Some(user_id) = &user_id {
+        for saved_query in app_state.user_store.saved_queries(user_id).await? {
+            folders
+                .entry(saved_query.folder.clone())
+                .or_default()
+                .push(saved_query);
+        }
+    }
+
+    let html = app_state
+        .templates
+        .get_template("saved.html")?
+        .render(context! {
+            session => session,
+            logged_in => user_id.is_some(),
+            folders,
+        })?;
+    Ok(Html(html))
+}
+
+#[serde_as]
+#[derive(Deserialize, Debug)]
+struct NewQueryParams {
+    #[serde(default)]
+    query: String,
+    #[serde(default)]
+    query_language: QueryLanguage,
+    #[serde(default)]
+    left_context: usize,
+    #[serde(default)]
+    right_context: usize,
+    #[serde(default, alias = "span_segmentation")]
+    #[serde_as(as = "NoneAsEmptyString")]
+    segmentation: Option<String>,
+}
+
+/// Show the form to save a query for the selected corpora.
+async fn new_query(
+    session: Session,
+    Query(params): Query<NewQueryParams>,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    let user_id = logged_in_user(&session, &app_state)?;
+    let saved_query = SavedQuery {
+        id: String::new(),
+        user_id,
+        folder: String::new(),
+        name: String::new(),
+        description: String::new(),
+        query: params.query,
+        query_language: params.query_language,
+        corpora: session.selected_corpora().iter().cloned().collect(),
+        left_context: params.left_context,
+        right_context: params.right_context,
+        segmentation: params.segmentation,
+    };
+
+    let html = app_state
+        .templates
+        .get_template("saved/query.html")?
+        .render(context! {
+            session => session,
+            saved_query,
+            is_owner => true,
+        })?;
+    Ok(Html(html))
+}
+
+/// Corpus names can contain commas, so the corpora are edited one per line.
+struct LineSeparator;
+
+impl Separator for LineSeparator {
+    fn separator() -> &'static str {
+        "\n"
+    }
+}
+
+#[serde_as]
+#[derive(Deserialize, Debug)]
+struct SavedQueryForm {
+    name: String,
+    #[serde(default)]
+    folder: String,
+    #[serde(default)]
+    description: String,
+    query: String,
+    #[serde(default)]
+    query_language: QueryLanguage,
+    #[serde(default)]
+    #[serde_as(as = "StringWithSeparator::<LineSeparator, String>")]
+    corpora: Vec<String>,
+    #[serde(default)]
+    left_context: usize,
+    #[serde(default)]
+    right_context: usize,
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    segmentation: Option<String>,
+}
+
+impl SavedQueryForm {
+    /// Set the fields of the saved query from the form. The query is used as
+    /// name if no name is given and the corpora are only changed if at least
+    /// one corpus is given.
+    fn apply(self, saved_query: &mut SavedQuery) {
+        saved_query.name = if self.name.trim().is_empty() {
+            self.query.clone()
+        } else {
+            self.name.trim().to_string()
+        };
+        saved_query.folder = self.folder.trim().to_string();
+        saved_query.description = self.description;
+        saved_query.query = self.query;
+        saved_query.query_language = self.query_language;
+        let corpora: Vec<String> = self
+            .corpora
+            .iter()
+            .map(|c| c.trim().to_string())
+            .filter(|c| !c.is_empty())
+            .collect();
+        if !corpora.is_empty() {
+            saved_query.corpora = corpora;
+        }
+        saved_query.left_context = self.left_context;
+        saved_query.right_context = self.right_context;
+        saved_query.segmentation = self.segmentation;
+    }
+}
+
+/// Save a new query for the selected corpora.
+async fn create(
+    session: Session,
+    State(app_state): State<Arc<GlobalAppState>>,
+    Form(form): Form<SavedQueryForm>,
+) -> Result<impl IntoResponse> {
+    let user_id = logged_in_user(&session, &app_state)?;
+    let mut saved_query = SavedQuery {
+        // The random ID also protects the queries from being listed by
+        // others, it is only known to the users the link is shared with
+        id: rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(ID_LENGTH)
+            .map(char::from)
+            .collect(),
+        user_id,
+        folder: String::new(),
+        name: String::new(),
+        description: String::new(),
+        query: String::new(),
+        query_language: QueryLanguage::AQL,
+        corpora: session.selected_corpora().iter().cloned().collect(),
+        left_context: 0,
+        right_context: 0,
+        segmentation: None,
+    };
+    form.apply(&mut saved_query);
+    app_state.user_store.save_query(&saved_query).await?;
+
+    Ok(redirect_to_query(&saved_query.id, &app_state))
+}
+
+/// Show a saved query. Everyone with the link can see it, but only its owner
+/// can change it.
+async fn show(
+    session: Session,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    let saved_query = app_state
+        .user_store
+        .saved_query(&id)
+        .await?
+        .ok_or(AppError::SavedQueryNotFound(id))?;
+    let is_owner = app_state.user_id(&session)?.as_ref() == Some(&saved_query.user_id);
+
+    let html = app_state
+        .templates
+        .get_template("saved/query.html")?
+        .render(context! {
+            session => session,
+            saved_query,
+            is_owner,
+        })?;
+    Ok(Html(html))
+}
+
+async fn update(
+    session: Session,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<GlobalAppState>>,
+    Form(form): Form<SavedQueryForm>,
+) -> Result<impl IntoResponse> {
+    let mut saved_query = owned_query(&id, &session, &app_state).await?;
+    form.apply(&mut saved_query);
+    app_state.user_store.save_query(&saved_query).await?;
+
+    Ok(redirect_to_query(&saved_query.id, &app_state))
+}
+
+async fn delete(
+    session: Session,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    let saved_query = owned_query(&id, &session, &app_state).await?;
+    app_state
+        .user_store
+        .delete_saved_query(&saved_query.id)
+        .await?;
+
+    Ok(Redirect::to(&format!("{}saved", app_state.frontend_prefix)))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{header::LOCATION, Method, Request, StatusCode},
+    Router,
+};
+use hyper::Response;
+use scraper::Selector;
+use test_log::test;
+use tower::ServiceExt;
+
+use crate::{
+    config::CliConfig,
+    state::GlobalAppState,
+    tests::{add_session, create_session_with_corpora, get_html, login_info},
+};
+
+async fn request(
+    app: &Router,
+    method: Method,
+    uri: &str,
+    cookie: &str,
+    form: &str,
+) -> Response<axum::body::BoxBody> {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("Cookie", cookie)
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body(Body::from(form.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+#[test(tokio::test)]
+async fn saved_query_lifecycle() {
+    let config = CliConfig::default();
+    let (owner_cookie, session_store) = create_session_with_corpora(&["pcc2"]).await;
+    let owner_id = owner_cookie.trim_start_matches("tower.sid=").to_string();
+    let (other_id, other_cookie) = add_session(&session_store).await;
+    let (_, anonymous_cookie) = add_session(&session_store).await;
+
+    let app_state = Arc::new(GlobalAppState::new(&config).unwrap());
+    app_state.login_info.insert(owner_id, login_info("owner"));
+    app_state.login_info.insert(other_id, login_info("other"));
+    let app = crate::app_with_state(app_state, session_store, chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+
+    // Only logged in users can save queries
+    let form = "name=Nouns&folder=POS&description=All+nouns&query=pos%3D%22NN%22&query_language=AQL&corpora=pcc2&left_context=5&right_context=3&segmentation=dipl";
+    let response = request(&app, Method::POST, "/saved", &anonymous_cookie, form).await;
+    assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+
+    let response = request(&app, Method::POST, "/saved", &owner_cookie, form).await;
+    assert_eq!(StatusCode::SEE_OTHER, response.status());
+    let location = response.headers()[LOCATION].to_str().unwrap().to_string();
+    let uri = location
+        .trim_start_matches("http://127.0.0.1:3000")
+        .to_string();
+    let id = uri.trim_start_matches("/saved/");
+    assert_eq!(24, id.len());
+    assert!(id.chars().all(|c| c.is_ascii_alphanumeric()));
+
+    let response = request(&app, Method::GET, "/saved", &owner_cookie, "").await;
+    let html = get_html(response).await;
+    let folders: Vec<_> = html
+        .select(&Selector::parse("#saved-queries h2").unwrap())
+        .map(|h| h.inner_html())
+        .collect();
+    assert_eq!(vec!["POS"], folders);
+    let names: Vec<_> = html
+        .select(&Selector::parse("td.saved-query-name a").unwrap())
+        .map(|a| a.inner_html())
+        .collect();
+    assert_eq!(vec!["Nouns"], names);
+
+    // Everyone with the link can see the query, but only the owner can edit it
+    let response = request(&app, Method::GET, &uri, &anonymous_cookie, "").await;
+    assert_eq!(StatusCode::OK, response.status());
+    let html = get_html(response).await;
+    let open = html
+        .select(&Selector::parse("#saved-query-open").unwrap())
+        .next()
+        .and_then(|a| a.value().attr("href"))
+        .map(str::to_string);
+    assert_eq!(
+        Some(
+            "http://127.0.0.1:3000/permalink?corpora=pcc2&left_context=5&query=pos%3D%22NN%22&query_language=AQL&right_context=3&segmentation=dipl"
+                .to_string()
+        ),
+        open
+    );
+    assert!(html
+        .select(&Selector::parse("#saved-query-form").unwrap())
+        .next()
+        .is_none());
+
+    let changed = "name=&folder=&description=&query=pos%3D%22ADJA%22&query_language=AQL&corpora=pcc2%0D%0Anews%2C+2023&left_context=2&right_context=2&segmentation=";
+    let response = request(&app, Method::POST, &uri, &other_cookie, changed).await;
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+    let response = request(&app, Method::POST, &uri, &owner_cookie, changed).await;
+    assert_eq!(StatusCode::SEE_OTHER, response.status());
+
+    // Without a name, the query is used as name
+    let response = request(&app, Method::GET, &uri, &owner_cookie, "").await;
+    let html = get_html(response).await;
+    let title = html
+        .select(&Selector::parse("h1.title").unwrap())
+        .next()
+        .map(|h| h.inner_html());
+    assert_eq!(Some("pos=\"ADJA\"".to_string()), title);
+    let open = html
+        .select(&Selector::parse("#saved-query-open").unwrap())
+        .next()
+        .and_then(|a| a.value().attr("href"))
+        .map(str::to_string);
+    assert_eq!(
+        Some(
+            "http://127.0.0.1:3000/permalink?corpora=pcc2&corpora=news%2C%202023&left_context=2&query=pos%3D%22ADJA%22&query_language=AQL&right_context=2&segmentation="
+                .to_string()
+        ),
+        open
+    );
+    assert!(html
+        .select(&Selector::parse("#saved-query-form").unwrap())
+        .next()
+        .is_some());
+    let corpora = html
+        .select(&Selector::parse("#saved-query-form textarea[name='corpora']").unwrap())
+        .next()
+        .map(|t| t.inner_html());
+    assert_eq!(Some("pcc2\nnews, 2023".to_string()), corpora);
+
+    let delete_uri = format!("{uri}/delete");
+    let response = request(&app, Method::POST, &delete_uri, &other_cookie, "").await;
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+    let response = request(&app, Method::POST, &delete_uri, &owner_cookie, "").await;
+    assert_eq!(StatusCode::SEE_OTHER, response.status());
+    let response = request(&app, Method::GET, &uri, &owner_cookie, "").await;
+    assert_eq!(StatusCode::NOT_FOUND, response.status());
+}
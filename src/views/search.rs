@@ -0,0 +1,491 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{Path, Query, State},
+    response::{Html, IntoResponse},
+    routing::get,
+    Router,
+};
+use graphannis::{
+    corpusstorage::{QueryLanguage, ResultOrder},
+    graph::NodeID,
+    AnnotationGraph,
+};
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use serde_with::{
+    formats::SpaceSeparator, serde_as, DisplayFromStr, NoneAsEmptyString, StringWithSeparator,
+};
+
+use crate::{
+    client::{
+        corpora::{
+            self, ContextConfiguration, CorpusConfiguration, VisualizerRule, VisualizerVisibility,
+        },
+        search::{self, CountQuery, FindQuery},
+    },
+    concordance::{self, KwicLine},
+    converter::CSVConfig,
+    errors::AppError,
+    history::{self, HistoryEntry},
+    state::{GlobalAppState, Session, SessionArg},
+    visualizer::{
+        self,
+        media::{self, MediaFile},
+        MatchContext, Visualization,
+    },
+    Result,
+};
+
+pub fn create_routes() -> Result<Router<Arc<GlobalAppState>>> {
+    let result = Router::new()
+        .route("/concordance", get(show_concordance))
+        .route("/match/:n", get(show_match))
+        .route("/match/:n/visualizer/:index", get(show_visualizer));
+    Ok(result)
+}
+
+/// Number of matches shown on a page of the concordance.
+const CONCORDANCE_PAGE_SIZE: usize = 10;
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ConcordanceParams {
+    query: String,
+    #[serde(default)]
+    query_language: QueryLanguage,
+    #[serde(flatten)]
+    config: CSVConfig,
+    /// Position of the first shown match in the (sorted) result.
+    #[serde(default)]
+    #[serde_as(as = "DisplayFromStr")]
+    offset: usize,
+}
+
+/// Show a page of the matches of the query with their context, sorted by
+/// the configured context position.
+async fn show_concordance(
+    session: Session,
+    Query(params): Query<ConcordanceParams>,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    let session_arg = SessionArg::Session(session.clone());
+    let app_state = app_state.as_ref();
+
+    let corpora: Vec<String> = session.selected_corpora().iter().cloned().collect();
+    let count_query = CountQuery {
+        query: params.query.clone(),
+        corpora: corpora.clone(),
+        query_language: params.query_language,
+    };
+    let match_count = search::count(&session_arg, &count_query, app_state)
+        .await?
+        .match_count;
+    // Only the first page counts as executing the query
+    if params.offset == 0 {
+        let entry = HistoryEntry::new(
+            &params.query,
+            params.query_language,
+            &params.config,
+            session.selected_corpora(),
+            Some(match_count),
+        );
+        history::record(&session, entry, app_state).await?;
+    }
+
+    // Without sorting, only the matches of the current page are fetched
+    let (page, total) = if let Some(position) = params.config.sort_position {
+        let find_query = FindQuery {
+            query: params.query.clone(),
+            corpora,
+            query_language: params.query_language,
+            limit: None,
+            offset: 0,
+            order: ResultOrder::Normal,
+        };
+        let matches = concordance::sorted_matches(
+            &session_arg,
+            &find_query,
+            position,
+            &params.config,
+            app_state,
+        )
+        .await?;
+        let page: Vec<_> = matches
+            .iter()
+            .skip(params.offset)
+            .take(CONCORDANCE_PAGE_SIZE)
+            .cloned()
+            .collect();
+        (page, matches.len())
+    } else {
+        let find_query = FindQuery {
+            query: params.query.clone(),
+            corpora,
+            query_language: params.query_language,
+            limit: Some(CONCORDANCE_PAGE_SIZE as u64),
+            offset: params.offset as u64,
+            order: ResultOrder::Normal,
+        };
+        let mut page = Vec::with_capacity(CONCORDANCE_PAGE_SIZE);
+        for m in search::find(&session_arg, &find_query, app_state)
+            .await?
+            .range(..)?
+        {
+            let (idx, node_ids) = m?;
+            page.push((params.offset as u64 + idx, node_ids));
+        }
+        (page, match_count as usize)
+    };
+
+    let segmentation = params.config.span_segmentation.clone();
+    let mut lines = Vec::new();
+    for (match_nr, node_ids) in page.iter() {
+        let corpus = node_ids
+            .first()
+            .and_then(|id| id.split_once('/'))
+            .map(|(corpus, _)| corpus)
+            .unwrap_or_default();
+        let g = corpora::subgraph(
+            &session_arg,
+            corpus,
+            node_ids.clone(),
+            segmentation.clone(),
+            params.config.left_context,
+            params.config.right_context,
+            app_state,
+        )
+        .await?;
+        let kwic = concordance::kwic_tokens(&g, node_ids, segmentation.as_deref())?;
+        lines.push(KwicLine::new(&g, *match_nr, &kwic)?);
+    }
+
+    let previous_page = (params.offset > 0).then(|| ConcordanceParams {
+        offset: params.offset.saturating_sub(CONCORDANCE_PAGE_SIZE),
+        ..params.clone()
+    });
+    let next_page = (params.offset + CONCORDANCE_PAGE_SIZE < total).then(|| ConcordanceParams {
+        offset: params.offset + CONCORDANCE_PAGE_SIZE,
+        ..params.clone()
+    });
+    let match_query = MatchQuery {
+        query: params.query.clone(),
+        query_language: params.query_language,
+        left: Some(params.config.left_context),
+        right: Some(params.config.right_context),
+        segmentation: Some(segmentation),
+    };
+
+    let html = app_state
+        .templates
+        .get_template("search/concordance.html")?
+        .render(context! {
+            lines,
+            offset => params.offset,
+            total,
+            match_count,
+            sort_position => params.config.sort_position.map(|p| p.to_string()),
+            sort_annotation => params.config.sort_annotation,
+            previous_page,
+            next_page,
+            match_query,
+        })?;
+
+    Ok(Html(html))
+}
+
+/// The query and context that define the match.
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct MatchQuery {
+    query: String,
+    #[serde(default)]
+    query_language: QueryLanguage,
+    /// Context size left of the match, the default context of the corpus is
+    /// used if not given.
+    #[serde(default, alias = "left_context")]
+    #[serde_as(as = "NoneAsEmptyString")]
+    left: Option<usize>,
+    /// Context size right of the match, the default context of the corpus is
+    /// used if not given.
+    #[serde(default, alias = "right_context")]
+    #[serde_as(as = "NoneAsEmptyString")]
+    right: Option<usize>,
+    /// Segmentation the context is defined in. The default segmentation of
+    /// the corpus is used if not given and an empty value selects tokens.
+    #[serde(default, alias = "span_segmentation")]
+    #[serde_as(as = "Option<NoneAsEmptyString>")]
+    segmentation: Option<Option<String>>,
+}
+
+#[serde_as]
+#[derive(Deserialize, Debug)]
+struct MatchParams {
+    #[serde(flatten)]
+    match_query: MatchQuery,
+    /// The node IDs of the match, if already known. Otherwise, the query is
+    /// executed to find the match.
+    #[serde(default)]
+    #[serde_as(as = "StringWithSeparator::<SpaceSeparator, String>")]
+    node_ids: Vec<String>,
+    /// Qualified name of the pointing component to show as arcs.
+    pointing: Option<String>,
+    /// Qualified name of the pointing component that aligns parallel texts.
+    alignment: Option<String>,
+}
+
+/// A visualizer rule and what it shows for the match. The visualization is
+/// only created in advance if the visualizer is expanded.
+#[derive(Serialize, Debug)]
+struct ShownVisualizer {
+    /// Position of the rule, used to load the visualizer later.
+    index: usize,
+    rule: VisualizerRule,
+    visualization: Option<Visualization>,
+}
+
+/// A match with its subgraph and everything needed to visualize it.
+struct LoadedMatch {
+    corpus: String,
+    node_ids: Vec<String>,
+    /// The query with the context sizes that have actually been used.
+    match_query: MatchQuery,
+    corpus_config: CorpusConfiguration,
+    g: AnnotationGraph,
+    matched: HashMap<NodeID, usize>,
+    media_files: Vec<MediaFile>,
+}
+
+impl LoadedMatch {
+    fn context<'a>(&'a self, params: &'a MatchParams) -> MatchContext<'a> {
+        MatchContext {
+            g: &self.g,
+            matched: &self.matched,
+            hidden_annos: &self.corpus_config.view.hidden_annos,
+            media_files: &self.media_files,
+            selected_pointing: params.pointing.as_deref(),
+            selected_alignment: params.alignment.as_deref(),
+        }
+    }
+
+    /// The visualizers configured for the corpus or the default ones.
+    fn rules(&self, params: &MatchParams) -> Result<Vec<VisualizerRule>> {
+        if self.corpus_config.visualizers.is_empty() {
+            visualizer::default_rules(&self.context(params))
+        } else {
+            Ok(self.corpus_config.visualizers.clone())
+        }
+    }
+}
+
+/// Get the match with the given number and its subgraph with the requested
+/// context, which is limited by the maximal context of the corpus. The query
+/// is only executed if the node IDs of the match are not given.
+async fn load_match(
+    session: &Session,
+    n: u64,
+    params: &MatchParams,
+    app_state: &GlobalAppState,
+) -> Result<LoadedMatch> {
+    let session_arg = SessionArg::Session(session.clone());
+    let match_query = &params.match_query;
+
+    let node_ids = if params.node_ids.is_empty() {
+        let find_query = FindQuery {
+            query: match_query.query.clone(),
+            corpora: session.selected_corpora().iter().cloned().collect(),
+            query_language: match_query.query_language,
+            limit: Some(1),
+            offset: n,
+            order: ResultOrder::Normal,
+        };
+        let matches = search::find(&session_arg, &find_query, app_state).await?;
+        matches.get(&0)?.ok_or(AppError::MatchNotFound(n))?
+    } else {
+        params.node_ids.clone()
+    };
+    let corpus = node_ids
+        .first()
+        .and_then(|id| id.split_once('/'))
+        .map(|(corpus, _)| corpus.to_string())
+        .unwrap_or_default();
+
+    let corpus_config = corpora::configuration(&session_arg, &corpus, app_state).await?;
+    let context = &corpus_config.context;
+    let limit = |size: Option<usize>| {
+        let size = size.unwrap_or(context.default);
+        context.max.map_or(size, |max| size.min(max))
+    };
+    let match_query = MatchQuery {
+        query: match_query.query.clone(),
+        query_language: match_query.query_language,
+        left: Some(limit(match_query.left)),
+        right: Some(limit(match_query.right)),
+        segmentation: Some(
+            match_query
+                .segmentation
+                .clone()
+                .unwrap_or_else(|| context.segmentation.clone()),
+        ),
+    };
+
+    let g = corpora::subgraph(
+        &session_arg,
+        &corpus,
+        node_ids.clone(),
+        match_query.segmentation.clone().flatten(),
+        match_query.left.unwrap_or_default(),
+        match_query.right.unwrap_or_default(),
+        app_state,
+    )
+    .await?;
+    let matched = visualizer::matched_nodes(&g, &node_ids)?;
+
+    // Find the linked audio and video files of the document. If the files
+    // can not be listed, only the media player is missing from the match
+    // details.
+    let document = node_ids
+        .first()
+        .and_then(|id| id.split_once('#'))
+        .map(|(document, _)| document);
+    let files = match corpora::list_files(&session_arg, &corpus, document, app_state).await {
+        Ok(files) => files,
+        Err(e) => {
+            tracing::warn!("Could not list the files of corpus {corpus}: {e}");
+            Vec::new()
+        }
+    };
+    let media_files = media::media_files(files);
+
+    Ok(LoadedMatch {
+        corpus,
+        node_ids,
+        match_query,
+        corpus_config,
+        g,
+        matched,
+        media_files,
+    })
+}
+
+/// The next larger context size, either from the configured sizes or by
+/// adding the default context. Returns `None` if the maximal context size is
+/// already reached.
+fn more_context(current: usize, config: &ContextConfiguration) -> Option<usize> {
+    let next = config
+        .sizes
+        .iter()
+        .copied()
+        .filter(|size| *size > current)
+        .min()
+        .unwrap_or(current + config.default.max(1));
+    let next = config.max.map_or(next, |max| next.min(max));
+    if next > current {
+        Some(next)
+    } else {
+        None
+    }
+}
+
+/// Show the details for the match with the given number (starting with 0)
+/// of the query. Expanded and preloaded visualizers are created right away,
+/// the others are loaded when the user expands them.
+async fn show_match(
+    session: Session,
+    Path(n): Path<u64>,
+    Query(params): Query<MatchParams>,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    let app_state = app_state.as_ref();
+    let m = load_match(&session, n, &params, app_state).await?;
+    let ctx = m.context(&params);
+
+    let mut visualizers = Vec::new();
+    for (index, rule) in m.rules(&params)?.into_iter().enumerate() {
+        match rule.visibility {
+            VisualizerVisibility::Visible
+            | VisualizerVisibility::Permanent
+            | VisualizerVisibility::Preloaded => {
+                // Omit created visualizers that have nothing to show
+                if let Some(visualization) = visualizer::create(&rule, &ctx)? {
+                    visualizers.push(ShownVisualizer {
+                        index,
+                        rule,
+                        visualization: Some(visualization),
+                    });
+                }
+            }
+            VisualizerVisibility::Hidden => {
+                visualizers.push(ShownVisualizer {
+                    index,
+                    rule,
+                    visualization: None,
+                });
+            }
+        }
+    }
+
+    let left = m.match_query.left.unwrap_or_default();
+    let right = m.match_query.right.unwrap_or_default();
+    let more_left_context = more_context(left, &m.corpus_config.context).map(|left| MatchQuery {
+        left: Some(left),
+        ..m.match_query.clone()
+    });
+    let more_right_context =
+        more_context(right, &m.corpus_config.context).map(|right| MatchQuery {
+            right: Some(right),
+            ..m.match_query.clone()
+        });
+
+    let html = app_state
+        .templates
+        .get_template("search/match.html")?
+        .render(context! {
+            n,
+            corpus => m.corpus,
+            node_ids => m.node_ids,
+            match_query => m.match_query,
+            more_left_context,
+            more_right_context,
+            visualizers,
+            media_files => m.media_files,
+        })?;
+
+    Ok(Html(html))
+}
+
+/// Show a single visualizer of the match, which is given by the position of
+/// its rule.
+async fn show_visualizer(
+    session: Session,
+    Path((n, index)): Path<(u64, usize)>,
+    Query(params): Query<MatchParams>,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    let app_state = app_state.as_ref();
+    let m = load_match(&session, n, &params, app_state).await?;
+
+    let visualization = if let Some(rule) = m.rules(&params)?.get(index) {
+        visualizer::create(rule, &m.context(&params))?
+    } else {
+        None
+    };
+
+    let html = app_state
+        .templates
+        .get_template("search/visualizer.html")?
+        .render(context! {
+            n,
+            index,
+            corpus => m.corpus,
+            node_ids => m.node_ids,
+            match_query => m.match_query,
+            visualization,
+            media_files => m.media_files,
+        })?;
+
+    Ok(Html(html))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,757 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use mockito::{Matcher, Mock, Server};
+use scraper::{Html, Selector};
+use test_log::test;
+use tower::ServiceExt;
+use tower_sessions::{session::SessionId, SessionStore};
+
+use crate::{
+    concordance::MAX_SORTED_MATCHES,
+    config::CliConfig,
+    state::GlobalAppState,
+    tests::{create_session_with_corpora, get_html},
+};
+
+const RIDGES_MATCH: &str =
+    "RIDGES_Herbology_Version9.0/Experimenta_1550_Schellenberg#sTok2771_virtualSpan";
+
+/// Mocks a backend that returns the given match, subgraph and linked files
+/// for the query "tok" and gets the match details.
+async fn get_match_detail(
+    service_mock: &mut Server,
+    corpus: &str,
+    match_ids: &str,
+    subgraph_file: &str,
+    corpus_configuration: &str,
+    linked_files: &str,
+) -> Html {
+    mock_match(
+        service_mock,
+        corpus,
+        match_ids,
+        subgraph_file,
+        corpus_configuration,
+        linked_files,
+    );
+    get_page(service_mock, corpus, "/search/match/0?query=tok").await
+}
+
+/// Mocks a backend that returns the given match, subgraph and linked files
+/// for the query "tok". Returns the mock for finding the match.
+fn mock_match(
+    service_mock: &mut Server,
+    corpus: &str,
+    match_ids: &str,
+    subgraph_file: &str,
+    corpus_configuration: &str,
+    linked_files: &str,
+) -> Mock {
+    let find_mock = service_mock
+        .mock("POST", "/search/find")
+        .match_body(Matcher::PartialJsonString(format!(
+            r#"{{"query": "tok", "corpora": ["{corpus}"], "limit": 1, "offset": 0}}"#
+        )))
+        .with_header("content-type", "text/plain")
+        .with_body(match_ids)
+        .create();
+    service_mock
+        .mock("POST", format!("/corpora/{corpus}/subgraph").as_str())
+        .with_body_from_file(subgraph_file)
+        .create();
+    service_mock
+        .mock("GET", format!("/corpora/{corpus}/configuration").as_str())
+        .with_header("content-type", "application/json")
+        .with_body(corpus_configuration)
+        .create();
+    let document = match_ids
+        .split_once('#')
+        .map(|(document, _)| document.rsplit("::").next().unwrap_or(document))
+        .unwrap_or_default();
+    service_mock
+        .mock("GET", format!("/corpora/{corpus}/files").as_str())
+        .match_query(Matcher::UrlEncoded("node".into(), document.into()))
+        .with_header("content-type", "application/json")
+        .with_body(linked_files)
+        .create();
+    find_mock
+}
+
+/// Get a page as a user that selected the given corpus.
+async fn get_page(service_mock: &Server, corpus: &str, uri: &str) -> Html {
+    let config = CliConfig {
+        service_url: service_mock.url(),
+        ..Default::default()
+    };
+    let (session_cookie, session_store) = create_session_with_corpora(&[corpus]).await;
+    let app_state = Arc::new(GlobalAppState::new(&config).unwrap());
+    let app = crate::app_with_state(app_state, session_store, chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(uri)
+                .header("Cookie", session_cookie)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    get_html(response).await
+}
+
+#[test(tokio::test)]
+async fn grid_for_match() {
+    let mut service_mock = Server::new_with_port(0);
+    let html = get_match_detail(
+        &mut service_mock,
+        "RIDGES_Herbology_Version9.0",
+        RIDGES_MATCH,
+        "tests/ridges-subgraph.graphml",
+        r#"{"view": {"hidden_annos": ["default_ns::lb", "default_ns::pb"]}}"#,
+        "[]",
+    )
+    .await;
+
+    let row_names: Vec<_> = html
+        .select(&Selector::parse(".grid-visualizer th").unwrap())
+        .map(|th| th.inner_html())
+        .collect();
+    assert_eq!(
+        vec![
+            "default_ns::abbr",
+            "default_ns::clean",
+            "default_ns::dipl",
+            "default_ns::lang",
+            "default_ns::lemma",
+            "default_ns::norm",
+            "default_ns::pos",
+            "default_ns::quote",
+            "default_ns::script",
+            "tok"
+        ],
+        row_names
+    );
+
+    let row_cells = |name: &str| -> Vec<(String, String, bool)> {
+        html.select(&Selector::parse(".grid-visualizer tr").unwrap())
+            .find(|tr| {
+                tr.select(&Selector::parse("th").unwrap())
+                    .any(|th| th.inner_html() == name)
+            })
+            .unwrap()
+            .select(&Selector::parse("td").unwrap())
+            .map(|td| {
+                (
+                    td.inner_html(),
+                    td.value().attr("colspan").unwrap_or_default().to_string(),
+                    td.value().attr("title").is_some(),
+                )
+            })
+            .collect()
+    };
+    let cell = |value: &str, colspan: &str, matched: bool| {
+        (value.to_string(), colspan.to_string(), matched)
+    };
+
+    assert_eq!(
+        vec![
+            cell("NN", "2", false),
+            cell("PTKZU", "1", false),
+            cell("VVINF", "1", false),
+            cell("VVINF", "1", false)
+        ],
+        row_cells("default_ns::pos")
+    );
+    assert_eq!(
+        vec![
+            cell("Baldrian", "1", false),
+            cell("waſſer", "1", true),
+            cell("zůtrincken", "2", false),
+            cell("geben", "1", false)
+        ],
+        row_cells("default_ns::dipl")
+    );
+}
+
+#[test(tokio::test)]
+async fn tree_for_match() {
+    let mut service_mock = Server::new_with_port(0);
+    let html = get_match_detail(
+        &mut service_mock,
+        "pcc2",
+        "tiger::pos::pcc2/4282#tok_73 tiger::pos::pcc2/4282#tok_74",
+        "tests/export-pcc2.graphml",
+        "{}",
+        "[]",
+    )
+    .await;
+
+    let trees: Vec<_> = html
+        .select(&Selector::parse(".tree-visualizer").unwrap())
+        .collect();
+    assert_eq!(1, trees.len());
+
+    let non_terminals: Vec<_> = trees[0]
+        .select(&Selector::parse("text.tree-non-terminal").unwrap())
+        .map(|t| t.inner_html())
+        .collect();
+    assert_eq!(vec!["NP", "VP", "CVP", "S"], non_terminals);
+    let terminals: Vec<_> = trees[0]
+        .select(&Selector::parse("text.tree-terminal").unwrap())
+        .map(|t| {
+            (
+                t.inner_html(),
+                t.value().attr("data-match").unwrap_or_default().to_string(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            ("haben".to_string(), "".to_string()),
+            ("den".to_string(), "1".to_string()),
+            ("Ball".to_string(), "2".to_string()),
+            ("erst".to_string(), "".to_string())
+        ],
+        terminals
+    );
+    let edge_labels: Vec<_> = trees[0]
+        .select(&Selector::parse("text.tree-edge-label").unwrap())
+        .map(|t| t.inner_html())
+        .collect();
+    assert_eq!(vec!["HD", "OC", "OA", "MO", "NK", "NK", "CJ"], edge_labels);
+}
+
+#[test(tokio::test)]
+async fn dependency_arcs_for_match() {
+    let mut service_mock = Server::new_with_port(0);
+    let html = get_match_detail(
+        &mut service_mock,
+        "pcc2",
+        "tiger::pos::pcc2/4282#tok_73 tiger::pos::pcc2/4282#tok_74",
+        "tests/export-pcc2.graphml",
+        "{}",
+        "[]",
+    )
+    .await;
+
+    let arcs: Vec<_> = html
+        .select(&Selector::parse(".arcs-visualizer path[marker-end]").unwrap())
+        .map(|p| p.value().attr("d").unwrap_or_default().to_string())
+        .collect();
+    assert_eq!(
+        vec!["M 150.0 59.0 C 150.0 35.0, 102.0 35.0, 102.0 59.0"],
+        arcs
+    );
+    let labels: Vec<_> = html
+        .select(&Selector::parse(".arcs-visualizer text.arc-label").unwrap())
+        .map(|t| t.inner_html())
+        .collect();
+    assert_eq!(vec!["det"], labels);
+    // There is only one pointing component, so there is nothing to select
+    assert_eq!(
+        0,
+        html.select(&Selector::parse("select[name='pointing']").unwrap())
+            .count()
+    );
+}
+
+#[test(tokio::test)]
+async fn media_player_for_time_aligned_match() {
+    let mut service_mock = Server::new_with_port(0);
+    let html = get_match_detail(
+        &mut service_mock,
+        "mediacorpus",
+        "mediacorpus/dialog1#t1 mediacorpus/dialog1#t2",
+        "tests/time-aligned-subgraph.graphml",
+        "{}",
+        r#"["dialog1.mp3", "dialog1.txt", "dialog 1.webm"]"#,
+    )
+    .await;
+
+    // Only audio and video files are shown, starting at the time of the match
+    let audio: Vec<_> = html
+        .select(&Selector::parse("audio.media-player").unwrap())
+        .map(|a| a.value().attr("src").unwrap_or_default().to_string())
+        .collect();
+    assert_eq!(
+        vec!["http://127.0.0.1:3000/corpora/mediacorpus/files/dialog1.mp3#t=0.5,2.0"],
+        audio
+    );
+    let video: Vec<_> = html
+        .select(&Selector::parse("video.media-player").unwrap())
+        .map(|v| v.value().attr("src").unwrap_or_default().to_string())
+        .collect();
+    assert_eq!(
+        vec!["http://127.0.0.1:3000/corpora/mediacorpus/files/dialog%201.webm#t=0.5,2.0"],
+        video
+    );
+
+    // Token and spans can be played individually
+    let token_times: Vec<_> = html
+        .select(&Selector::parse(".grid-token td").unwrap())
+        .map(|t| t.value().attr("data-time").unwrap_or_default().to_string())
+        .collect();
+    assert_eq!(vec!["t=0.5,1.25", "t=1.25,2.0", "t=3.5,4.0"], token_times);
+}
+
+#[test(tokio::test)]
+async fn alignment_for_parallel_match() {
+    let mut service_mock = Server::new_with_port(0);
+    let html = get_match_detail(
+        &mut service_mock,
+        "parallel",
+        "parallel/doc1#e2",
+        "tests/parallel-subgraph.graphml",
+        "{}",
+        "[]",
+    )
+    .await;
+
+    let texts: Vec<_> = html
+        .select(&Selector::parse(".alignment-visualizer .aligned-text").unwrap())
+        .map(|t| {
+            t.select(&Selector::parse("text").unwrap())
+                .map(|t| t.inner_html())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect();
+    assert_eq!(vec!["the cat sleeps", "die Katze schläft"], texts);
+    let mut lines: Vec<_> = html
+        .select(&Selector::parse(".alignment-visualizer line").unwrap())
+        .map(|l| {
+            let attr = |name| l.value().attr(name).unwrap_or_default().to_string();
+            (attr("x1"), attr("y1"), attr("x2"), attr("y2"))
+        })
+        .collect();
+    lines.sort_by(|a, b| a.0.parse::<f64>().unwrap().total_cmp(&b.0.parse().unwrap()));
+    assert_eq!(3, lines.len());
+    // The aligned token are connected from the bottom of the upper text to
+    // the top of the lower text
+    assert!(lines
+        .iter()
+        .all(|(_, y1, _, y2)| y1 == "42.0" && y2 == "110.0"));
+    let x: Vec<_> = lines
+        .iter()
+        .map(|(x1, _, x2, _)| (x1.as_str(), x2.as_str()))
+        .collect();
+    assert_eq!(
+        vec![("42.0", "42.0"), ("86.0", "94.0"), ("142.0", "162.0")],
+        x
+    );
+}
+
+#[test(tokio::test)]
+async fn visualizers_from_corpus_configuration() {
+    let mut service_mock = Server::new_with_port(0);
+    let html = get_match_detail(
+        &mut service_mock,
+        "pcc2",
+        "tiger::pos::pcc2/4282#tok_73 tiger::pos::pcc2/4282#tok_74",
+        "tests/export-pcc2.graphml",
+        r#"{"visualizers": [
+            {"element": "node", "layer": "tiger", "vis_type": "tree", "display_name": "Syntax",
+             "visibility": "permanent", "mappings": {"edge_key": "unknown"}},
+            {"element": "node", "vis_type": "grid", "display_name": "Part of speech",
+             "mappings": {"annos": "tiger::pos"}},
+            {"element": "node", "vis_type": "kwic", "display_name": "KWIC", "visibility": "permanent"}
+        ]}"#,
+        "[]",
+    )
+    .await;
+
+    // Only the configured and known visualizers are shown, in the given order
+    let names: Vec<_> = html
+        .select(&Selector::parse(".visualizer h4, .visualizer summary").unwrap())
+        .map(|n| n.inner_html())
+        .collect();
+    assert_eq!(vec!["Syntax", "Part of speech"], names);
+    assert_eq!(
+        0,
+        html.select(&Selector::parse(".arcs-visualizer").unwrap())
+            .count()
+    );
+
+    // The tree is always shown and uses the configured edge labels
+    let tree = html
+        .select(&Selector::parse("div.visualizer .tree-visualizer").unwrap())
+        .next()
+        .unwrap();
+    assert_eq!(
+        0,
+        tree.select(&Selector::parse("text.tree-edge-label").unwrap())
+            .count()
+    );
+
+    // The grid is collapsed by default and only shows the listed annotation
+    assert_eq!(
+        1,
+        html.select(
+            &Selector::parse("details.visualizer:not([open]) .visualizer-content").unwrap()
+        )
+        .count()
+    );
+    let html = get_page(
+        &service_mock,
+        "pcc2",
+        "/search/match/0/visualizer/1?query=tok",
+    )
+    .await;
+    let row_names: Vec<_> = html
+        .select(&Selector::parse(".grid-visualizer th").unwrap())
+        .map(|th| th.inner_html())
+        .collect();
+    assert_eq!(vec!["tiger::pos", "tok"], row_names);
+}
+
+#[test(tokio::test)]
+async fn hidden_visualizers_are_loaded_on_demand() {
+    let mut service_mock = Server::new_with_port(0);
+    let find_mock = mock_match(
+        &mut service_mock,
+        "pcc2",
+        "tiger::pos::pcc2/4282#tok_73 tiger::pos::pcc2/4282#tok_74",
+        "tests/export-pcc2.graphml",
+        r#"{"visualizers": [
+            {"vis_type": "grid", "display_name": "Grid", "visibility": "visible"},
+            {"layer": "tiger", "vis_type": "tree", "display_name": "Syntax", "visibility": "hidden"},
+            {"layer": "dep", "vis_type": "arch_dependency", "display_name": "Dependencies",
+             "visibility": "preloaded"}
+        ]}"#,
+        "[]",
+    );
+    let html = get_page(&service_mock, "pcc2", "/search/match/0?query=tok").await;
+
+    // The visible grid and the preloaded arcs are part of the match details,
+    // but the arcs are collapsed
+    assert_eq!(
+        1,
+        html.select(&Selector::parse("details[open] .grid-visualizer").unwrap())
+            .count()
+    );
+    assert_eq!(
+        1,
+        html.select(&Selector::parse("details:not([open]) .arcs-visualizer").unwrap())
+            .count()
+    );
+    assert_eq!(
+        0,
+        html.select(&Selector::parse(".tree-visualizer").unwrap())
+            .count()
+    );
+    let lazy: Vec<_> = html
+        .select(&Selector::parse(".visualizer-content[hx-get]").unwrap())
+        .map(|c| {
+            (
+                c.value().attr("hx-get").unwrap_or_default().to_string(),
+                c.value().attr("hx-trigger").unwrap_or_default().to_string(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        vec![(
+            "http://127.0.0.1:3000/search/match/0/visualizer/1?left=5&query=tok&query_language=AQL&right=5&segmentation=&node_ids=pcc2/4282%23tok_73%20pcc2/4282%23tok_74"
+                .to_string(),
+            "toggle once from:closest details".to_string()
+        )],
+        lazy
+    );
+
+    // The hidden tree is loaded separately, without executing the query again
+    let html = get_page(
+        &service_mock,
+        "pcc2",
+        "/search/match/0/visualizer/1?left=5&query=tok&query_language=AQL&right=5&segmentation=&node_ids=pcc2/4282%23tok_73%20pcc2/4282%23tok_74",
+    )
+    .await;
+    assert_eq!(
+        1,
+        html.select(&Selector::parse(".tree-visualizer").unwrap())
+            .count()
+    );
+    find_mock.assert();
+}
+
+#[test(tokio::test)]
+async fn match_without_file_listing() {
+    let mut service_mock = Server::new_with_port(0);
+    // The backend can not list the files, e.g. because it does not support it
+    let files_mock = service_mock
+        .mock("GET", "/corpora/pcc2/files")
+        .match_query(Matcher::Any)
+        .with_status(404)
+        .create();
+    mock_match(
+        &mut service_mock,
+        "pcc2",
+        "tiger::pos::pcc2/4282#tok_73 tiger::pos::pcc2/4282#tok_74",
+        "tests/export-pcc2.graphml",
+        r#"{"visualizers": [{"vis_type": "grid", "display_name": "Grid", "visibility": "visible"}]}"#,
+        "[]",
+    );
+    let html = get_page(&service_mock, "pcc2", "/search/match/0?query=tok").await;
+
+    // The other visualizers are still shown
+    assert_eq!(
+        1,
+        html.select(&Selector::parse(".grid-visualizer").unwrap())
+            .count()
+    );
+    files_mock.assert();
+}
+
+#[test(tokio::test)]
+async fn match_with_more_context() {
+    let mut service_mock = Server::new_with_port(0);
+    mock_match(
+        &mut service_mock,
+        "pcc2",
+        "tiger::pos::pcc2/4282#tok_73 tiger::pos::pcc2/4282#tok_74",
+        "tests/export-pcc2.graphml",
+        r#"{"context": {"default": 2, "sizes": [1, 2, 5], "max": 10}}"#,
+        "[]",
+    );
+
+    let more_context = |html: &Html| -> Vec<String> {
+        html.select(&Selector::parse("button.more-context").unwrap())
+            .map(|b| b.value().attr("hx-get").unwrap_or_default().to_string())
+            .collect()
+    };
+
+    // Use the default context and offer the next larger size
+    let html = get_page(&service_mock, "pcc2", "/search/match/0?query=tok").await;
+    assert_eq!(
+        vec![
+            "http://127.0.0.1:3000/search/match/0?left=5&query=tok&query_language=AQL&right=2&segmentation=",
+            "http://127.0.0.1:3000/search/match/0?left=2&query=tok&query_language=AQL&right=5&segmentation=",
+        ],
+        more_context(&html)
+    );
+
+    // The context is limited by the maximal context size and grows by the
+    // default context after the largest configured size
+    let html = get_page(
+        &service_mock,
+        "pcc2",
+        "/search/match/0?query=tok&left=20&right=5",
+    )
+    .await;
+    assert_eq!(
+        vec!["http://127.0.0.1:3000/search/match/0?left=10&query=tok&query_language=AQL&right=7&segmentation="],
+        more_context(&html)
+    );
+}
+
+#[test(tokio::test)]
+async fn match_context_in_tokens() {
+    let mut service_mock = Server::new_with_port(0);
+    mock_match(
+        &mut service_mock,
+        "pcc2",
+        "tiger::pos::pcc2/4282#tok_73 tiger::pos::pcc2/4282#tok_74",
+        "tests/export-pcc2.graphml",
+        r#"{"context": {"default": 2, "sizes": [2], "max": null, "segmentation": "dipl"}}"#,
+        "[]",
+    );
+
+    let more_left_context = |html: &Html| -> Option<String> {
+        html.select(&Selector::parse("button.more-context").unwrap())
+            .next()
+            .and_then(|b| b.value().attr("hx-get"))
+            .map(str::to_string)
+    };
+
+    // The segmentation of the corpus is used by default
+    let html = get_page(&service_mock, "pcc2", "/search/match/0?query=tok").await;
+    assert_eq!(
+        Some("http://127.0.0.1:3000/search/match/0?left=4&query=tok&query_language=AQL&right=2&segmentation=dipl"),
+        more_left_context(&html).as_deref()
+    );
+
+    // An empty segmentation selects the tokens
+    let html = get_page(
+        &service_mock,
+        "pcc2",
+        "/search/match/0?query=tok&segmentation=",
+    )
+    .await;
+    assert_eq!(
+        Some("http://127.0.0.1:3000/search/match/0?left=4&query=tok&query_language=AQL&right=2&segmentation="),
+        more_left_context(&html).as_deref()
+    );
+}
+
+#[test(tokio::test)]
+async fn match_in_compatibility_mode() {
+    let mut service_mock = Server::new_with_port(0);
+    let quirks_mock = service_mock
+        .mock("POST", "/search/find")
+        .match_body(Matcher::PartialJsonString(
+            r#"{"query": "tok", "query_language": "AQLQuirksV3"}"#.to_string(),
+        ))
+        .with_header("content-type", "text/plain")
+        .with_body("tiger::pos::pcc2/4282#tok_73 tiger::pos::pcc2/4282#tok_74")
+        .create();
+    mock_match(
+        &mut service_mock,
+        "pcc2",
+        "tiger::pos::pcc2/4282#tok_73 tiger::pos::pcc2/4282#tok_74",
+        "tests/export-pcc2.graphml",
+        "{}",
+        "[]",
+    );
+
+    let html = get_page(
+        &service_mock,
+        "pcc2",
+        "/search/match/0?query=tok&query_language=AQLQuirksV3",
+    )
+    .await;
+    let more_context: Vec<_> = html
+        .select(&Selector::parse("button.more-context").unwrap())
+        .map(|b| b.value().attr("hx-get").unwrap_or_default().to_string())
+        .collect();
+    assert!(more_context
+        .iter()
+        .all(|url| url.contains("query_language=AQLQuirksV3")));
+    quirks_mock.assert();
+}
+
+/// Mocks a backend with three matches in the coreference test document.
+fn mock_coreference_matches(service_mock: &mut Server) {
+    service_mock
+        .mock("POST", "/search/find")
+        .with_header("content-type", "text/plain")
+        .with_body("corefcorpus/doc1#t2\ncorefcorpus/doc1#t3\ncorefcorpus/doc1#t6\n")
+        .create();
+    service_mock
+        .mock("POST", "/corpora/corefcorpus/subgraph")
+        .with_body_from_file("tests/coreference-document.graphml")
+        .create();
+    service_mock
+        .mock("POST", "/search/count")
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"match_count": 3, "document_count": 1}"#)
+        .create();
+}
+
+#[test(tokio::test)]
+async fn concordance_sorted_by_context() {
+    let mut service_mock = Server::new_with_port(0);
+    mock_coreference_matches(&mut service_mock);
+
+    let kwic = |html: &Html, column: &str| -> Vec<String> {
+        html.select(&Selector::parse(&format!(".kwic td.kwic-{column}")).unwrap())
+            .map(|td| td.inner_html())
+            .collect()
+    };
+
+    // Without sorting, the matches are in their original order
+    let html = get_page(
+        &service_mock,
+        "corefcorpus",
+        "/search/concordance?query=tok",
+    )
+    .await;
+    assert_eq!(vec!["traf", "Maria", "grüßte"], kwic(&html, "match"));
+
+    // Sort by the token left of the match
+    let html = get_page(
+        &service_mock,
+        "corefcorpus",
+        "/search/concordance?query=tok&left_context=5&right_context=5&sort_position=L1",
+    )
+    .await;
+    assert_eq!(vec!["grüßte", "traf", "Maria"], kwic(&html, "match"));
+    assert_eq!(
+        vec!["Peter traf Maria. Er", "Peter", "Peter traf"],
+        kwic(&html, "left")
+    );
+
+    // Sort by the annotation of the span covering the token left of the
+    // match, matches without this annotation come last
+    let html = get_page(
+        &service_mock,
+        "corefcorpus",
+        "/search/concordance?query=tok&left_context=5&right_context=5&sort_position=L1&sort_annotation=mmax::np_form",
+    )
+    .await;
+    assert_eq!(vec!["traf", "grüßte", "Maria"], kwic(&html, "match"));
+}
+
+#[test(tokio::test)]
+async fn concordance_sorts_limited_matches() {
+    let mut service_mock = Server::new_with_port(0);
+    let find_mock = service_mock
+        .mock("POST", "/search/find")
+        .match_body(Matcher::PartialJsonString(format!(
+            r#"{{"limit": {MAX_SORTED_MATCHES}, "offset": 0}}"#
+        )))
+        .with_header("content-type", "text/plain")
+        .with_body("corefcorpus/doc1#t2\ncorefcorpus/doc1#t3\ncorefcorpus/doc1#t6\n")
+        .create();
+    service_mock
+        .mock("POST", "/corpora/corefcorpus/subgraph")
+        .with_body_from_file("tests/coreference-document.graphml")
+        .create();
+    service_mock
+        .mock("POST", "/search/count")
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"match_count": 5000, "document_count": 1}"#)
+        .create();
+
+    let config = CliConfig {
+        service_url: service_mock.url(),
+        ..Default::default()
+    };
+    let (session_cookie, session_store) = create_session_with_corpora(&["corefcorpus"]).await;
+    let session_id = session_cookie.trim_start_matches("tower.sid=").to_string();
+    let app_state = Arc::new(GlobalAppState::new(&config).unwrap());
+    let app = crate::app_with_state(
+        app_state.clone(),
+        session_store.clone(),
+        chrono::Duration::seconds(60),
+    )
+    .await
+    .unwrap();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(
+                    "/search/concordance?query=tok&left_context=5&right_context=5&sort_position=L1",
+                )
+                .header("Cookie", session_cookie)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = get_html(response).await;
+    let warning = html
+        .select(&Selector::parse("#concordance .notification.is-warning").unwrap())
+        .next()
+        .map(|n| n.text().collect::<String>())
+        .unwrap_or_default();
+    assert!(warning.contains("Only the first 3 of 5000 matches can be sorted."));
+    find_mock.assert();
+
+    // The sorted matches are only kept as long as the session exists
+    app_state.cleanup(&session_store).await;
+    assert!(app_state.sorted_matches.contains_key(&session_id));
+    session_store
+        .delete(&SessionId::try_from(session_id.as_str()).unwrap())
+        .await
+        .unwrap();
+    app_state.cleanup(&session_store).await;
+    assert!(!app_state.sorted_matches.contains_key(&session_id));
+}
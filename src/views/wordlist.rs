@@ -0,0 +1,227 @@
+use std::sync::Arc;
+
+use axum::{
+    body::StreamBody,
+    extract::{Query, State},
+    http::header,
+    response::{Html, IntoResponse},
+    routing::{delete, get, post},
+    Form, Router,
+};
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
+use tokio::{sync::mpsc::channel, task::JoinHandle};
+use tokio_util::io::ReaderStream;
+
+use crate::{
+    errors::AppError,
+    state::{ExportJob, GlobalAppState, JobState, Session, SessionArg},
+    views::export::common_segmentations,
+    wordlist::{self, WordListConfig},
+    Result,
+};
+
+/// Number of entries shown in the word list table, the complete list can be
+/// downloaded as CSV.
+const WORD_LIST_PAGE_SIZE: usize = 100;
+
+pub fn create_routes() -> Result<Router<Arc<GlobalAppState>>> {
+    let result = Router::new()
+        .route("/", get(show_page))
+        .route("/job", post(create_job))
+        .route("/job", get(job_status))
+        .route("/job", delete(cancel_job))
+        .route("/table", get(show_table))
+        .route("/file", get(download_file));
+    Ok(result)
+}
+
+async fn show_page(
+    session: Session,
+    Query(config): Query<WordListConfig>,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    let segmentations = common_segmentations(&session, &app_state).await;
+    let has_word_list = app_state.word_lists.contains_key(session.id())
+        || app_state.word_list_jobs.contains_key(session.id());
+
+    let html = app_state
+        .templates
+        .get_template("wordlist.html")?
+        .render(context! {
+            session => session,
+            config,
+            segmentations,
+            has_word_list,
+            job => current_job(&session, &app_state),
+        })?;
+    Ok(Html(html))
+}
+
+async fn create_job(
+    session: Session,
+    State(app_state): State<Arc<GlobalAppState>>,
+    Form(config): Form<WordListConfig>,
+) -> Result<impl IntoResponse> {
+    // Only allow one word list job per session
+    let session_arg = SessionArg::Id(session.id().to_string());
+    app_state
+        .word_list_jobs
+        .entry(session_arg.id())
+        .or_insert_with(|| {
+            let corpora: Vec<String> = session.selected_corpora().iter().cloned().collect();
+            let app_state_copy = app_state.clone();
+            let (sender, receiver) = channel(1);
+            let handle: JoinHandle<Result<NamedTempFile>> = tokio::spawn(async move {
+                let entries = wordlist::create(
+                    &session_arg,
+                    &corpora,
+                    &config,
+                    &app_state_copy,
+                    Some(sender),
+                )
+                .await?;
+                let mut result_file = tempfile::NamedTempFile::new()?;
+                wordlist::write_csv(&entries, &mut result_file)?;
+                Ok(result_file)
+            });
+            ExportJob::new(handle, receiver)
+        });
+
+    render_job(&session, &app_state)
+}
+
+async fn job_status(
+    session: Session,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    render_job(&session, &app_state)
+}
+
+async fn cancel_job(
+    session: Session,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    if let Some((_, job)) = app_state.word_list_jobs.remove(session.id()) {
+        job.handle.abort();
+    }
+    render_job(&session, &app_state)
+}
+
+fn render_job(session: &Session, app_state: &GlobalAppState) -> Result<Html<String>> {
+    let html = app_state
+        .templates
+        .get_template("wordlist/job.html")?
+        .render(context! {
+            job => current_job(session, app_state),
+        })?;
+    Ok(Html(html))
+}
+
+fn current_job(session: &Session, app_state: &GlobalAppState) -> JobState {
+    if let Some(mut job) = app_state.word_list_jobs.get_mut(session.id()) {
+        job.state()
+    } else {
+        JobState::Idle
+    }
+}
+
+/// Move the result of a finished job to the finished word lists.
+async fn collect_finished_job(session_id: &str, app_state: &GlobalAppState) -> Result<()> {
+    let is_finished = app_state
+        .word_list_jobs
+        .get(session_id)
+        .is_some_and(|job| job.handle.is_finished());
+    if is_finished {
+        if let Some((_, job)) = app_state.word_list_jobs.remove(session_id) {
+            let file = job.handle.await??;
+            app_state.word_lists.insert(session_id.to_string(), file);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+enum WordListSort {
+    #[default]
+    Count,
+    Value,
+}
+
+#[derive(Deserialize, Debug)]
+struct TableParams {
+    #[serde(default)]
+    sort: WordListSort,
+    #[serde(default)]
+    descending: bool,
+}
+
+async fn show_table(
+    session: Session,
+    Query(params): Query<TableParams>,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    collect_finished_job(session.id(), &app_state).await?;
+
+    let mut entries = if let Some(file) = app_state.word_lists.get(session.id()) {
+        wordlist::read_csv(file.reopen()?)?
+    } else {
+        Vec::new()
+    };
+    let total = entries.len();
+    match params.sort {
+        // Values with the same count stay in alphabetical order when sorted
+        // descending
+        WordListSort::Count => {
+            entries.sort_by(|a, b| a.count.cmp(&b.count).then_with(|| b.value.cmp(&a.value)))
+        }
+        WordListSort::Value => entries.sort_by_cached_key(|e| e.value.to_lowercase()),
+    }
+    if params.descending {
+        entries.reverse();
+    }
+    entries.truncate(WORD_LIST_PAGE_SIZE);
+
+    let html = app_state
+        .templates
+        .get_template("wordlist/table.html")?
+        .render(context! {
+            entries,
+            total,
+            sort => params.sort,
+            descending => params.descending,
+        })?;
+    Ok(Html(html))
+}
+
+async fn download_file(
+    session: Session,
+    State(app_state): State<Arc<GlobalAppState>>,
+) -> Result<impl IntoResponse> {
+    collect_finished_job(session.id(), &app_state).await?;
+
+    let path = app_state
+        .word_lists
+        .get(session.id())
+        .map(|file| file.path().to_path_buf());
+    if let Some(path) = path {
+        let tokio_file = tokio::fs::File::open(path).await?;
+        let stream = ReaderStream::new(tokio_file);
+        let body = StreamBody::new(stream);
+
+        let mut headers = header::HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, "text/csv; charset=utf-8".parse()?);
+        headers.insert(
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"annis-word-list.csv\"".parse()?,
+        );
+        Ok((headers, body))
+    } else {
+        Err(AppError::DownloadFileNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,171 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    body::Body,
+    http::{header::CONTENT_TYPE, Request, StatusCode},
+    Router,
+};
+use mockito::{Matcher, Server};
+use scraper::{Html, Selector};
+use test_log::test;
+use tower::ServiceExt;
+use tower_sessions::{session::SessionId, SessionStore};
+
+use crate::{
+    config::CliConfig,
+    state::GlobalAppState,
+    tests::{create_session_with_corpora, get_body, get_html},
+};
+
+async fn request(
+    app: &Router,
+    session_cookie: &str,
+    method: &str,
+    uri: &str,
+    body: &str,
+) -> String {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("Cookie", session_cookie)
+                .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    get_body(response).await
+}
+
+#[test(tokio::test)]
+async fn create_bigram_list() {
+    let mut service_mock = Server::new_with_port(0);
+    service_mock
+        .mock("POST", "/search/frequency")
+        .match_body(Matcher::PartialJsonString(
+            r#"{"query": "tok . tok", "corpora": ["pcc2"]}"#.to_string(),
+        ))
+        .with_header("content-type", "application/json")
+        .with_body(
+            r#"[
+                {"values": ["die", "Jugendlichen"], "count": 2},
+                {"values": ["in", "Zossen"], "count": 3}
+            ]"#,
+        )
+        .create();
+    service_mock
+        .mock("POST", "/search/frequency")
+        .match_body(Matcher::PartialJsonString(
+            r#"{"query": "tok . tok", "corpora": ["RIDGES"]}"#.to_string(),
+        ))
+        .with_header("content-type", "application/json")
+        .with_body(r#"[{"values": ["die", "Jugendlichen"], "count": 2}]"#)
+        .create();
+
+    let config = CliConfig {
+        service_url: service_mock.url(),
+        ..Default::default()
+    };
+    let (session_cookie, session_store) = create_session_with_corpora(&["RIDGES", "pcc2"]).await;
+    let session_id = session_cookie.trim_start_matches("tower.sid=").to_string();
+    let app_state = Arc::new(GlobalAppState::new(&config).unwrap());
+    let app = crate::app_with_state(
+        app_state.clone(),
+        session_store.clone(),
+        chrono::Duration::seconds(60),
+    )
+    .await
+    .unwrap();
+
+    request(
+        &app,
+        &session_cookie,
+        "POST",
+        "/wordlist/job",
+        "n=2&segmentation=&annotation=&meta_name=&meta_value=",
+    )
+    .await;
+    // Wait until the job is finished
+    let mut status = String::new();
+    for _ in 0..50 {
+        status = request(&app, &session_cookie, "GET", "/wordlist/job", "").await;
+        if status.contains("finished") {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(status.contains("The word list is finished."));
+
+    // The counts of both corpora are summed up
+    let cells = |html: &Html, column: &str| -> Vec<String> {
+        html.select(&Selector::parse(&format!("td.word-list-{column}")).unwrap())
+            .map(|td| td.inner_html())
+            .collect()
+    };
+    let html = Html::parse_document(
+        &request(
+            &app,
+            &session_cookie,
+            "GET",
+            "/wordlist/table?sort=count&descending=true",
+            "",
+        )
+        .await,
+    );
+    assert_eq!(vec!["die Jugendlichen", "in Zossen"], cells(&html, "value"));
+    assert_eq!(vec!["4", "3"], cells(&html, "count"));
+
+    let html = Html::parse_document(
+        &request(
+            &app,
+            &session_cookie,
+            "GET",
+            "/wordlist/table?sort=value&descending=true",
+            "",
+        )
+        .await,
+    );
+    assert_eq!(vec!["in Zossen", "die Jugendlichen"], cells(&html, "value"));
+
+    let csv = request(&app, &session_cookie, "GET", "/wordlist/file", "").await;
+    assert_eq!("value,count\ndie Jugendlichen,4\nin Zossen,3\n", csv);
+
+    // The page loads the finished word list
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/wordlist")
+                .header("Cookie", session_cookie)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let html = get_html(response).await;
+    assert_eq!(
+        Some("http://127.0.0.1:3000/wordlist/table?sort=count&descending=true"),
+        html.select(&Selector::parse("#word-list-table").unwrap())
+            .next()
+            .and_then(|e| e.value().attr("hx-get"))
+    );
+
+    // The word list file is deleted when the session does not exist anymore
+    app_state.cleanup(&session_store).await;
+    let file = app_state
+        .word_lists
+        .get(&session_id)
+        .map(|f| f.path().to_path_buf())
+        .unwrap();
+    assert!(file.exists());
+    session_store
+        .delete(&SessionId::try_from(session_id.as_str()).unwrap())
+        .await
+        .unwrap();
+    app_state.cleanup(&session_store).await;
+    assert!(!app_state.word_lists.contains_key(&session_id));
+    assert!(!file.exists());
+}
@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+
+use graphannis::{
+    graph::{GraphStorage, NodeID},
+    model::{AnnotationComponent, AnnotationComponentType},
+    AnnotationGraph,
+};
+use graphannis_core::{
+    graph::ANNIS_NS,
+    types::Component,
+    util::{join_qname, split_qname},
+};
+use serde::Serialize;
+
+use crate::{
+    client::corpora::{VisualizerRule, VisualizerVisibility},
+    Result,
+};
+
+pub mod alignment;
+pub mod arcs;
+pub mod discourse;
+pub mod grid;
+pub mod media;
+pub mod tree;
+
+/// The output of a visualizer for a match, which is rendered by the template
+/// for its kind.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Visualization {
+    Grid {
+        grid: grid::Grid,
+    },
+    Tree {
+        tree: tree::Tree,
+    },
+    Arcs {
+        arcs: arcs::Arcs,
+        /// Qualified names of the pointing components that can be selected.
+        component_names: Vec<String>,
+    },
+    Alignment {
+        alignment: alignment::Alignment,
+        /// Qualified names of the pointing components that can be selected.
+        component_names: Vec<String>,
+    },
+    Media {
+        files: Vec<media::MediaFile>,
+        time_range: Option<media::TimeRange>,
+    },
+}
+
+/// Everything the visualizers need to know about a match.
+pub struct MatchContext<'a> {
+    pub g: &'a AnnotationGraph,
+    pub matched: &'a HashMap<NodeID, usize>,
+    /// Qualified annotation names that should not be shown.
+    pub hidden_annos: &'a [String],
+    /// Audio and video files linked to the document of the match.
+    pub media_files: &'a [media::MediaFile],
+    /// Qualified name of the pointing component selected for the arcs.
+    pub selected_pointing: Option<&'a str>,
+    /// Qualified name of the pointing component selected for the alignment.
+    pub selected_alignment: Option<&'a str>,
+}
+
+/// The visualizers to show if the corpus configuration does not define any:
+/// a grid, a tree for each dominance layer with categories, arcs and the
+/// alignment for pointing relations and players for linked media files.
+pub fn default_rules(ctx: &MatchContext) -> Result<Vec<VisualizerRule>> {
+    let rule = |vis_type: &str, layer: Option<String>, display_name: String| VisualizerRule {
+        element: None,
+        layer,
+        vis_type: vis_type.to_string(),
+        display_name,
+        visibility: VisualizerVisibility::Visible,
+        mappings: None,
+    };
+    let mut result = vec![rule("grid", None, "Grid".to_string())];
+    for layer in tree::tree_layers(ctx.g, "cat")? {
+        let display_name = format!("Tree ({layer})");
+        result.push(rule("tree", Some(layer), display_name));
+    }
+    result.push(rule("arch_dependency", None, "Arcs".to_string()));
+    result.push(rule("alignment", None, "Alignment".to_string()));
+    if ctx.media_files.iter().any(|f| !f.is_video) {
+        result.push(rule("audio", None, "Audio".to_string()));
+    }
+    if ctx.media_files.iter().any(|f| f.is_video) {
+        result.push(rule("video", None, "Video".to_string()));
+    }
+    Ok(result)
+}
+
+/// Create the visualization for the given rule. Returns `None` if the
+/// visualizer type is unknown or there is nothing to show for the match,
+/// e.g. because the graph has no components in the configured layer.
+pub fn create(rule: &VisualizerRule, ctx: &MatchContext) -> Result<Option<Visualization>> {
+    let result = match rule.vis_type.as_str() {
+        "grid" => {
+            let mut grid = grid::create(ctx.g, ctx.matched, ctx.hidden_annos)?;
+            // Only show the annotations of the layer and the listed names
+            let annos: Option<Vec<&str>> = rule
+                .mapping("annos")
+                .map(|annos| annos.split(',').map(|a| a.trim()).collect());
+            grid.rows.retain(|row| {
+                let (ns, name) = split_qname(&row.name);
+                let in_layer = rule.layer.is_none() || rule.layer.as_deref() == ns;
+                let listed = match &annos {
+                    Some(annos) => annos.contains(&row.name.as_str()) || annos.contains(&name),
+                    None => true,
+                };
+                in_layer && listed
+            });
+            Some(Visualization::Grid { grid })
+        }
+        "tree" => {
+            let Some(layer) = &rule.layer else {
+                return Ok(None);
+            };
+            let mut config = tree::TreeConfig::new(layer);
+            if let Some(node_key) = rule.mapping("node_key") {
+                config.node_key = node_key.to_string();
+            }
+            if let Some(edge_key) = rule.mapping("edge_key") {
+                config.edge_key = edge_key.to_string();
+            }
+            tree::create(ctx.g, &config, ctx.matched)?.map(|tree| Visualization::Tree { tree })
+        }
+        "arch_dependency" => {
+            let components = pointing_components(ctx.g, rule);
+            let component_names = qualified_names(&components);
+            let selected = ctx
+                .selected_pointing
+                .and_then(|selected| component_names.iter().position(|n| n == selected))
+                .unwrap_or_default();
+            if let Some(component) = components.get(selected) {
+                let edge_key = rule.mapping("edge_key").unwrap_or("func");
+                let arcs = arcs::create(ctx.g, component, edge_key, ctx.matched)?;
+                Some(Visualization::Arcs {
+                    arcs,
+                    component_names,
+                })
+            } else {
+                None
+            }
+        }
+        "alignment" => {
+            let texts = alignment::texts(ctx.g)?;
+            let components = pointing_components(ctx.g, rule);
+            let component_names = qualified_names(&components);
+            let selected = ctx
+                .selected_alignment
+                .and_then(|selected| component_names.iter().position(|n| n == selected))
+                .or_else(|| alignment::default_component(&components))
+                .unwrap_or_default();
+            match components.get(selected) {
+                Some(component) if texts.len() > 1 => {
+                    let alignment = alignment::create(ctx.g, &texts, component, ctx.matched)?;
+                    Some(Visualization::Alignment {
+                        alignment,
+                        component_names,
+                    })
+                }
+                _ => None,
+            }
+        }
+        "audio" | "video" => {
+            let is_video = rule.vis_type == "video";
+            let files: Vec<_> = ctx
+                .media_files
+                .iter()
+                .filter(|f| f.is_video == is_video)
+                .cloned()
+                .collect();
+            if files.is_empty() {
+                None
+            } else {
+                Some(Visualization::Media {
+                    files,
+                    time_range: media::match_time_range(ctx.g, ctx.matched)?,
+                })
+            }
+        }
+        _ => None,
+    };
+    Ok(result)
+}
+
+/// The pointing components in the layer of the rule or all of them, if the
+/// rule has no layer.
+fn pointing_components(g: &AnnotationGraph, rule: &VisualizerRule) -> Vec<AnnotationComponent> {
+    arcs::pointing_components(g)
+        .into_iter()
+        .filter(|c| rule.layer.is_none() || rule.layer.as_deref() == Some(c.layer.as_str()))
+        .collect()
+}
+
+fn qualified_names(components: &[AnnotationComponent]) -> Vec<String> {
+    components
+        .iter()
+        .map(|c| join_qname(&c.layer, &c.name))
+        .collect()
+}
+
+/// Maps the nodes of a match to their 1-based position in the match. Nodes
+/// that are not part of the subgraph are ignored.
+pub fn matched_nodes(g: &AnnotationGraph, node_names: &[String]) -> Result<HashMap<NodeID, usize>> {
+    let mut result = HashMap::new();
+    for (idx, name) in node_names.iter().enumerate() {
+        if let Some(id) = g.get_node_id_from_name(name)? {
+            result.entry(id).or_insert(idx + 1);
+        }
+    }
+    Ok(result)
+}
+
+/// Collect the edges that connect the last token before a gap in the
+/// subgraph with the first token after it.
+pub(crate) fn gap_edges(g: &AnnotationGraph) -> Result<bimap::BiHashMap<NodeID, NodeID>> {
+    let datasource_gap_component = Component::new(
+        AnnotationComponentType::Ordering,
+        ANNIS_NS.into(),
+        "datasource-gap".into(),
+    );
+    let mut result = bimap::BiHashMap::new();
+    if let Some(gs) = g.get_graphstorage_as_ref(&datasource_gap_component) {
+        for source in gs.source_nodes() {
+            let source = source?;
+            for target in gs.get_outgoing_edges(source) {
+                result.insert(source, target?);
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Get all graph storages of the given component type.
+fn graph_storages(
+    g: &AnnotationGraph,
+    ctype: AnnotationComponentType,
+) -> Vec<std::sync::Arc<dyn GraphStorage>> {
+    g.get_all_components(Some(ctype), None)
+        .into_iter()
+        .filter_map(|c| g.get_graphstorage(&c))
+        .collect()
+}
+
+/// Assign a level (starting with 1) to each of the given token intervals, so
+/// that overlapping intervals are on different levels. Each interval is put
+/// above all overlapping intervals that come before it, so shorter
+/// intervals should be given first.
+fn stacked_levels(intervals: &[(usize, usize)]) -> Vec<usize> {
+    let mut levels: Vec<usize> = Vec::with_capacity(intervals.len());
+    for (idx, (left, right)) in intervals.iter().enumerate() {
+        let level = intervals[..idx]
+            .iter()
+            .zip(levels.iter())
+            .filter(|((other_left, other_right), _)| other_left <= right && other_right >= left)
+            .map(|(_, level)| *level)
+            .max()
+            .unwrap_or_default()
+            + 1;
+        levels.push(level);
+    }
+    levels
+}
+
+#[cfg(test)]
+mod tests;
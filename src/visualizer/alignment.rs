@@ -0,0 +1,244 @@
+//! The alignment visualizer shows the texts of a parallel corpus one below
+//! the other and connects the aligned token or spans with lines. Which units
+//! are aligned is defined by the edges of a pointing component.
+
+use std::collections::{BTreeSet, HashMap};
+
+use graphannis::{
+    graph::{AnnoKey, NodeID},
+    model::{AnnotationComponent, AnnotationComponentType},
+    AnnotationGraph,
+};
+use graphannis_core::{
+    annostorage::ValueSearch, graph::ANNIS_NS, types::Component, util::join_qname,
+};
+use serde::Serialize;
+
+use super::{gap_edges, graph_storages};
+use crate::Result;
+
+/// Horizontal space reserved for each character of a token.
+const CHAR_WIDTH: f64 = 8.0;
+/// Minimal horizontal space reserved for each token.
+const MIN_TOKEN_WIDTH: f64 = 40.0;
+/// Vertical distance between the baselines of two texts.
+const TEXT_DISTANCE: f64 = 90.0;
+const MARGIN: f64 = 20.0;
+/// Space above the baseline occupied by the token.
+const TOKEN_ASCENT: f64 = 16.0;
+/// Space below the baseline occupied by the token.
+const TOKEN_DESCENT: f64 = 6.0;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AlignmentToken {
+    pub x: f64,
+    pub label: String,
+    /// Position of the token in the match, if it is matched.
+    pub matched: Option<usize>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AlignedText {
+    /// Layer of the first token or a generic name if there is none.
+    pub name: String,
+    /// Vertical position of the token.
+    pub baseline: f64,
+    pub token: Vec<AlignmentToken>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AlignmentLine {
+    pub x1: f64,
+    pub y1: f64,
+    pub x2: f64,
+    pub y2: f64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Alignment {
+    /// Qualified name of the pointing component used for the alignment.
+    pub component: String,
+    pub width: f64,
+    pub height: f64,
+    pub texts: Vec<AlignedText>,
+    pub lines: Vec<AlignmentLine>,
+}
+
+/// Get the token of each text in the graph. Texts are separate chains of
+/// the token ordering, which are continued over gaps in the subgraph.
+pub fn texts(g: &AnnotationGraph) -> Result<Vec<Vec<NodeID>>> {
+    let ordering_component = Component::new(
+        AnnotationComponentType::Ordering,
+        ANNIS_NS.into(),
+        "".into(),
+    );
+    let Some(ordering_gs) = g.get_graphstorage_as_ref(&ordering_component) else {
+        return Ok(Vec::new());
+    };
+    let gap_edges = gap_edges(g)?;
+    let coverage = graph_storages(g, AnnotationComponentType::Coverage);
+
+    let mut roots = BTreeSet::new();
+    for m in g
+        .get_node_annos()
+        .exact_anno_search(Some(ANNIS_NS), "tok", ValueSearch::Any)
+    {
+        let node = m?.node;
+        let mut actual_token = true;
+        for gs in coverage.iter() {
+            if gs.has_outgoing_edges(node)? {
+                actual_token = false;
+                break;
+            }
+        }
+        if actual_token
+            && ordering_gs.get_ingoing_edges(node).next().is_none()
+            && !gap_edges.contains_right(&node)
+        {
+            roots.insert(node);
+        }
+    }
+
+    let mut result = Vec::with_capacity(roots.len());
+    for root in roots {
+        let mut text = Vec::new();
+        let mut token = Some(root);
+        while let Some(current) = token {
+            text.push(current);
+            token = match ordering_gs.get_outgoing_edges(current).next() {
+                Some(next) => Some(next?),
+                None => gap_edges.get_by_left(&current).copied(),
+            };
+        }
+        result.push(text);
+    }
+    Ok(result)
+}
+
+/// Returns the pointing component that should be used for the alignment by
+/// default: the first one with "align" in its name or layer.
+pub fn default_component(components: &[AnnotationComponent]) -> Option<usize> {
+    components
+        .iter()
+        .position(|c| c.name.contains("align") || c.layer.contains("align"))
+}
+
+/// Create the alignment view for the given texts, connecting the units that
+/// are linked by an edge of the given pointing component. Spans are
+/// connected at the middle of their covered token.
+pub fn create(
+    g: &AnnotationGraph,
+    texts: &[Vec<NodeID>],
+    component: &AnnotationComponent,
+    matched: &HashMap<NodeID, usize>,
+) -> Result<Alignment> {
+    let token_value_key = AnnoKey {
+        ns: ANNIS_NS.into(),
+        name: "tok".into(),
+    };
+    let layer_key = AnnoKey {
+        ns: ANNIS_NS.into(),
+        name: "layer".into(),
+    };
+
+    let mut aligned_texts = Vec::with_capacity(texts.len());
+    let mut token_position: HashMap<NodeID, (usize, f64)> = HashMap::new();
+    let mut width: f64 = 0.0;
+    for (text_idx, text) in texts.iter().enumerate() {
+        let baseline = MARGIN + TOKEN_ASCENT + text_idx as f64 * TEXT_DISTANCE;
+        let mut x = MARGIN;
+        let mut token = Vec::with_capacity(text.len());
+        for t in text {
+            let label = g
+                .get_node_annos()
+                .get_value_for_item(t, &token_value_key)?
+                .unwrap_or_default()
+                .to_string();
+            let token_width =
+                (label.chars().count() as f64 * CHAR_WIDTH + MARGIN).max(MIN_TOKEN_WIDTH);
+            token_position.insert(*t, (text_idx, x + token_width / 2.0));
+            token.push(AlignmentToken {
+                x: x + token_width / 2.0,
+                label,
+                matched: matched.get(t).copied(),
+            });
+            x += token_width;
+        }
+        width = width.max(x + MARGIN);
+        let name = match text.first() {
+            Some(first) => g.get_node_annos().get_value_for_item(first, &layer_key)?,
+            None => None,
+        }
+        .map(|layer| layer.to_string())
+        .unwrap_or_else(|| format!("Text {}", text_idx + 1));
+        aligned_texts.push(AlignedText {
+            name,
+            baseline,
+            token,
+        });
+    }
+
+    let coverage = graph_storages(g, AnnotationComponentType::Coverage);
+    let position_of = |node: NodeID| -> Result<Option<(usize, f64)>> {
+        if let Some(pos) = token_position.get(&node) {
+            return Ok(Some(*pos));
+        }
+        let mut covered = Vec::new();
+        for gs in coverage.iter() {
+            for t in gs.get_outgoing_edges(node) {
+                if let Some(pos) = token_position.get(&t?) {
+                    covered.push(*pos);
+                }
+            }
+        }
+        let Some((text_idx, _)) = covered.first().copied() else {
+            return Ok(None);
+        };
+        let min = covered.iter().map(|(_, x)| *x).fold(f64::MAX, f64::min);
+        let max = covered.iter().map(|(_, x)| *x).fold(f64::MIN, f64::max);
+        Ok(Some((text_idx, (min + max) / 2.0)))
+    };
+
+    let mut lines = Vec::new();
+    if let Some(gs) = g.get_graphstorage(component) {
+        for source in gs.source_nodes() {
+            let source = source?;
+            for target in gs.get_outgoing_edges(source) {
+                let target = target?;
+                let (Some(source_pos), Some(target_pos)) =
+                    (position_of(source)?, position_of(target)?)
+                else {
+                    continue;
+                };
+                // Only units in different texts are aligned
+                if source_pos.0 == target_pos.0 {
+                    continue;
+                }
+                let (upper, lower) = if source_pos.0 < target_pos.0 {
+                    (source_pos, target_pos)
+                } else {
+                    (target_pos, source_pos)
+                };
+                lines.push(AlignmentLine {
+                    x1: upper.1,
+                    y1: aligned_texts[upper.0].baseline + TOKEN_DESCENT,
+                    x2: lower.1,
+                    y2: aligned_texts[lower.0].baseline - TOKEN_ASCENT,
+                });
+            }
+        }
+    }
+
+    let height = aligned_texts
+        .last()
+        .map(|t| t.baseline + TOKEN_DESCENT + MARGIN)
+        .unwrap_or_default();
+
+    Ok(Alignment {
+        component: join_qname(&component.layer, &component.name),
+        width,
+        height,
+        texts: aligned_texts,
+        lines,
+    })
+}
@@ -0,0 +1,191 @@
+//! The arc visualizer shows pointing relations, e.g. dependencies, as
+//! labeled arcs above the token.
+
+use std::collections::{BTreeSet, HashMap};
+
+use graphannis::{
+    graph::{AnnoKey, NodeID},
+    model::{AnnotationComponent, AnnotationComponentType},
+    AnnotationGraph,
+};
+use graphannis_core::{graph::ANNIS_NS, types::Edge, util::join_qname};
+use serde::Serialize;
+
+use super::{gap_edges, graph_storages, stacked_levels};
+use crate::{converter::ordered_tokens, Result};
+
+/// Horizontal space reserved for each character of a token.
+const CHAR_WIDTH: f64 = 8.0;
+/// Minimal horizontal space reserved for each token.
+const MIN_TOKEN_WIDTH: f64 = 40.0;
+/// Vertical distance between two arc levels.
+const LEVEL_HEIGHT: f64 = 25.0;
+const MARGIN: f64 = 20.0;
+/// Space below the arcs for the token.
+const TOKEN_HEIGHT: f64 = 30.0;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ArcToken {
+    pub x: f64,
+    pub label: String,
+    /// Position of the token in the match, if it is matched.
+    pub matched: Option<usize>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DependencyArc {
+    /// Horizontal position of the source.
+    pub x1: f64,
+    /// Horizontal position of the target.
+    pub x2: f64,
+    /// Vertical position of the highest point of the arc.
+    pub top: f64,
+    pub label: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Arcs {
+    /// Qualified name of the pointing component.
+    pub component: String,
+    pub width: f64,
+    pub height: f64,
+    /// Vertical position of the token.
+    pub baseline: f64,
+    pub token: Vec<ArcToken>,
+    pub arcs: Vec<DependencyArc>,
+}
+
+/// Returns all pointing components of the graph, except the internal ones
+/// in the "annis" layer.
+pub fn pointing_components(g: &AnnotationGraph) -> Vec<AnnotationComponent> {
+    g.get_all_components(Some(AnnotationComponentType::Pointing), None)
+        .into_iter()
+        .filter(|c| c.layer != ANNIS_NS)
+        .collect()
+}
+
+/// An arc between two token positions, before the arc height is known.
+struct Relation {
+    source: usize,
+    target: usize,
+    label: Option<String>,
+}
+
+impl Relation {
+    fn left(&self) -> usize {
+        self.source.min(self.target)
+    }
+
+    fn right(&self) -> usize {
+        self.source.max(self.target)
+    }
+}
+
+/// Create the arcs for the edges of the given pointing component. Edges
+/// between spans are attached to the first token covered by the span.
+/// The label of an arc is the edge annotation with the name `edge_key`.
+pub fn create(
+    g: &AnnotationGraph,
+    component: &AnnotationComponent,
+    edge_key: &str,
+    matched: &HashMap<NodeID, usize>,
+) -> Result<Arcs> {
+    let token_value_key = AnnoKey {
+        ns: ANNIS_NS.into(),
+        name: "tok".into(),
+    };
+    let mut token = Vec::new();
+    let mut token_position: HashMap<NodeID, usize> = HashMap::new();
+    let mut width = MARGIN;
+    for (idx, t) in ordered_tokens(g, None, &gap_edges(g)?)?
+        .into_iter()
+        .enumerate()
+    {
+        let label = g
+            .get_node_annos()
+            .get_value_for_item(&t.node, &token_value_key)?
+            .unwrap_or_default()
+            .to_string();
+        let token_width = (label.chars().count() as f64 * CHAR_WIDTH + MARGIN).max(MIN_TOKEN_WIDTH);
+        token.push(ArcToken {
+            x: width + token_width / 2.0,
+            label,
+            matched: matched.get(&t.node).copied(),
+        });
+        token_position.insert(t.node, idx);
+        width += token_width;
+    }
+    width += MARGIN;
+
+    let coverage = graph_storages(g, AnnotationComponentType::Coverage);
+    let position_of = |node: NodeID| -> Result<Option<usize>> {
+        if let Some(pos) = token_position.get(&node) {
+            return Ok(Some(*pos));
+        }
+        let mut covered = BTreeSet::new();
+        for gs in coverage.iter() {
+            for t in gs.get_outgoing_edges(node) {
+                if let Some(pos) = token_position.get(&t?) {
+                    covered.insert(*pos);
+                }
+            }
+        }
+        Ok(covered.first().copied())
+    };
+
+    let mut relations = Vec::new();
+    if let Some(gs) = g.get_graphstorage(component) {
+        for source in gs.source_nodes() {
+            let source = source?;
+            for target in gs.get_outgoing_edges(source) {
+                let target = target?;
+                if let (Some(source_pos), Some(target_pos)) =
+                    (position_of(source)?, position_of(target)?)
+                {
+                    let label = gs
+                        .get_anno_storage()
+                        .get_annotations_for_item(&Edge { source, target })?
+                        .into_iter()
+                        .find(|a| a.key.name == edge_key)
+                        .map(|a| a.val.to_string());
+                    relations.push(Relation {
+                        source: source_pos,
+                        target: target_pos,
+                        label,
+                    });
+                }
+            }
+        }
+    }
+
+    // Place shorter arcs first, so they are below the longer ones
+    relations.sort_by_key(|r| (r.right() - r.left(), r.left()));
+    let levels = stacked_levels(
+        &relations
+            .iter()
+            .map(|r| (r.left(), r.right()))
+            .collect::<Vec<_>>(),
+    );
+    let max_level = levels.iter().copied().max().unwrap_or_default();
+    let baseline = MARGIN + max_level as f64 * LEVEL_HEIGHT + TOKEN_HEIGHT;
+
+    let arcs = relations
+        .into_iter()
+        .zip(levels)
+        .map(|(r, level)| DependencyArc {
+            x1: token[r.source].x,
+            x2: token[r.target].x,
+            top: baseline - TOKEN_HEIGHT / 2.0 - level as f64 * LEVEL_HEIGHT,
+            label: r.label,
+        })
+        .collect();
+
+    Ok(Arcs {
+        component: join_qname(&component.layer, &component.name),
+        width,
+        height: baseline + MARGIN,
+        baseline,
+        token,
+        arcs,
+    })
+}
@@ -0,0 +1,152 @@
+//! The discourse visualizer shows the text of a whole document with the
+//! referring expressions underlined. Expressions that are connected by
+//! pointing relations, e.g. anaphoric relations, form a coreference chain and
+//! share the same color.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use graphannis::{
+    graph::{AnnoKey, NodeID},
+    model::{AnnotationComponent, AnnotationComponentType},
+    AnnotationGraph,
+};
+use graphannis_core::graph::ANNIS_NS;
+use serde::Serialize;
+
+use super::{gap_edges, graph_storages, stacked_levels};
+use crate::{converter::ordered_tokens, Result};
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DiscourseToken {
+    pub text: String,
+    pub whitespace_after: String,
+    /// The coreference chain of the referring expression on each underline
+    /// level, starting with the one closest to the text. `None` if there is no
+    /// expression covering this token on that level.
+    pub underlines: Vec<Option<usize>>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Discourse {
+    pub token: Vec<DiscourseToken>,
+    /// Number of distinct coreference chains.
+    pub number_of_chains: usize,
+}
+
+/// Create the discourse view for the given graph, using the edges of the
+/// given pointing components as coreference relations.
+pub fn create(g: &AnnotationGraph, components: &[AnnotationComponent]) -> Result<Discourse> {
+    let tokens = ordered_tokens(g, None, &gap_edges(g)?)?;
+    let token_position: HashMap<NodeID, usize> = tokens
+        .iter()
+        .enumerate()
+        .map(|(idx, t)| (t.node, idx))
+        .collect();
+
+    // Find the connected referring expressions, each node is mapped to a
+    // representative of its chain
+    let mut representative: BTreeMap<NodeID, NodeID> = BTreeMap::new();
+    for c in components {
+        if let Some(gs) = g.get_graphstorage(c) {
+            for source in gs.source_nodes() {
+                let source = source?;
+                for target in gs.get_outgoing_edges(source) {
+                    let target = target?;
+                    let a = find_representative(&mut representative, source);
+                    let b = find_representative(&mut representative, target);
+                    if a != b {
+                        representative.insert(a.max(b), a.min(b));
+                    }
+                }
+            }
+        }
+    }
+
+    // Get the covered token range of each referring expression
+    let coverage = graph_storages(g, AnnotationComponentType::Coverage);
+    let mut mentions = Vec::new();
+    let nodes: Vec<NodeID> = representative.keys().copied().collect();
+    for n in nodes {
+        let mut covered = BTreeSet::new();
+        if let Some(pos) = token_position.get(&n) {
+            covered.insert(*pos);
+        }
+        for gs in coverage.iter() {
+            for t in gs.get_outgoing_edges(n) {
+                if let Some(pos) = token_position.get(&t?) {
+                    covered.insert(*pos);
+                }
+            }
+        }
+        if let (Some(left), Some(right)) = (covered.first(), covered.last()) {
+            let chain = find_representative(&mut representative, n);
+            mentions.push((*left, *right, chain));
+        }
+    }
+
+    // Number the chains in the order of their first mention
+    mentions.sort_by_key(|(left, right, _)| (*left, *right));
+    let mut chain_number: HashMap<NodeID, usize> = HashMap::new();
+    for (_, _, chain) in mentions.iter() {
+        let next_number = chain_number.len();
+        chain_number.entry(*chain).or_insert(next_number);
+    }
+
+    // Underline shorter expressions closer to the text
+    mentions.sort_by_key(|(left, right, _)| (right - left, *left));
+    let levels = stacked_levels(
+        &mentions
+            .iter()
+            .map(|(left, right, _)| (*left, *right))
+            .collect::<Vec<_>>(),
+    );
+    let max_level = levels.iter().copied().max().unwrap_or_default();
+
+    let token_value_key = AnnoKey {
+        ns: ANNIS_NS.into(),
+        name: "tok".into(),
+    };
+    let whitespace_after_key = AnnoKey {
+        ns: ANNIS_NS.into(),
+        name: "tok-whitespace-after".into(),
+    };
+    let mut token = Vec::with_capacity(tokens.len());
+    for t in tokens {
+        let text = g
+            .get_node_annos()
+            .get_value_for_item(&t.node, &token_value_key)?
+            .unwrap_or_default()
+            .to_string();
+        let whitespace_after = g
+            .get_node_annos()
+            .get_value_for_item(&t.node, &whitespace_after_key)?
+            .map(|ws| ws.to_string())
+            .unwrap_or_else(|| " ".to_string());
+        token.push(DiscourseToken {
+            text,
+            whitespace_after,
+            underlines: vec![None; max_level],
+        });
+    }
+    for ((left, right, chain), level) in mentions.into_iter().zip(levels) {
+        for t in &mut token[left..=right] {
+            t.underlines[level - 1] = chain_number.get(&chain).copied();
+        }
+    }
+
+    Ok(Discourse {
+        token,
+        number_of_chains: chain_number.len(),
+    })
+}
+
+fn find_representative(representative: &mut BTreeMap<NodeID, NodeID>, node: NodeID) -> NodeID {
+    let mut current = *representative.entry(node).or_insert(node);
+    while let Some(next) = representative.get(&current).copied() {
+        if next == current {
+            break;
+        }
+        current = next;
+    }
+    current
+}
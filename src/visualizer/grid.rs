@@ -0,0 +1,173 @@
+//! The grid visualizer shows the token of a match in a table, with a row for
+//! each annotation of the covering spans stacked above them.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use graphannis::{
+    graph::{AnnoKey, NodeID},
+    model::AnnotationComponentType,
+    AnnotationGraph,
+};
+use graphannis_core::{annostorage::ValueSearch, graph::ANNIS_NS, util::join_qname};
+use serde::Serialize;
+
+use super::{
+    gap_edges, graph_storages,
+    media::{time_range, TimeRange},
+};
+use crate::{converter::ordered_tokens, Result};
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct GridCell {
+    pub value: String,
+    /// Number of token this cell spans.
+    pub colspan: usize,
+    /// Position of the annotated node in the match, if it is matched.
+    pub matched: Option<usize>,
+    /// Time range of the annotated node in a linked audio or video file.
+    pub time: Option<TimeRange>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct GridRow {
+    /// Qualified annotation name shown in this row.
+    pub name: String,
+    pub cells: Vec<GridCell>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Grid {
+    /// One row for each annotation, annotations with overlapping spans use
+    /// several rows with the same name.
+    pub rows: Vec<GridRow>,
+    pub token: Vec<GridCell>,
+}
+
+/// An annotation value and the range of token positions it covers.
+struct Span {
+    left: usize,
+    right: usize,
+    value: String,
+    node: NodeID,
+}
+
+/// Create the grid for the given subgraph. The `matched` nodes map to their
+/// position in the match and annotations in `hidden_annos` are omitted.
+pub fn create(
+    g: &AnnotationGraph,
+    matched: &HashMap<NodeID, usize>,
+    hidden_annos: &[String],
+) -> Result<Grid> {
+    let tokens = ordered_tokens(g, None, &gap_edges(g)?)?;
+    let token_position: HashMap<NodeID, usize> = tokens
+        .iter()
+        .enumerate()
+        .map(|(idx, t)| (t.node, idx))
+        .collect();
+
+    let token_value_key = AnnoKey {
+        ns: ANNIS_NS.into(),
+        name: "tok".into(),
+    };
+    let mut token = Vec::with_capacity(tokens.len());
+    for t in tokens.iter() {
+        let value = g
+            .get_node_annos()
+            .get_value_for_item(&t.node, &token_value_key)?
+            .unwrap_or_default();
+        token.push(GridCell {
+            value: value.to_string(),
+            colspan: 1,
+            matched: matched.get(&t.node).copied(),
+            time: time_range(g, t.node)?,
+        });
+    }
+
+    // Find the token range of all annotated nodes
+    let coverage = graph_storages(g, AnnotationComponentType::Coverage);
+    let mut spans_by_anno: BTreeMap<String, Vec<Span>> = BTreeMap::new();
+    for m in
+        g.get_node_annos()
+            .exact_anno_search(Some(ANNIS_NS), "node_type", ValueSearch::Some("node"))
+    {
+        let node = m?.node;
+        let mut covered: BTreeSet<usize> = BTreeSet::new();
+        if let Some(pos) = token_position.get(&node) {
+            covered.insert(*pos);
+        }
+        for gs in coverage.iter() {
+            for target in gs.get_outgoing_edges(node) {
+                if let Some(pos) = token_position.get(&target?) {
+                    covered.insert(*pos);
+                }
+            }
+        }
+        let (Some(left), Some(right)) = (covered.first(), covered.last()) else {
+            continue;
+        };
+        for anno in g.get_node_annos().get_annotations_for_item(&node)? {
+            let name = join_qname(&anno.key.ns, &anno.key.name);
+            if anno.key.ns != ANNIS_NS && !hidden_annos.contains(&name) {
+                spans_by_anno.entry(name).or_default().push(Span {
+                    left: *left,
+                    right: *right,
+                    value: anno.val.to_string(),
+                    node,
+                });
+            }
+        }
+    }
+
+    let mut rows = Vec::new();
+    for (name, mut spans) in spans_by_anno {
+        spans.sort_by_key(|s| (s.left, s.right));
+        // Put each span in the first row where it does not overlap with
+        // the already added spans
+        let mut stacked: Vec<Vec<Span>> = Vec::new();
+        for s in spans {
+            if let Some(row) = stacked
+                .iter_mut()
+                .find(|row| row.last().is_some_and(|last| last.right < s.left))
+            {
+                row.push(s);
+            } else {
+                stacked.push(vec![s]);
+            }
+        }
+        for row in stacked {
+            let mut cells = Vec::new();
+            let mut next_position = 0;
+            for s in row {
+                if s.left > next_position {
+                    cells.push(GridCell {
+                        value: String::default(),
+                        colspan: s.left - next_position,
+                        matched: None,
+                        time: None,
+                    });
+                }
+                cells.push(GridCell {
+                    value: s.value,
+                    colspan: s.right - s.left + 1,
+                    matched: matched.get(&s.node).copied(),
+                    time: time_range(g, s.node)?,
+                });
+                next_position = s.right + 1;
+            }
+            if next_position < token.len() {
+                cells.push(GridCell {
+                    value: String::default(),
+                    colspan: token.len() - next_position,
+                    matched: None,
+                    time: None,
+                });
+            }
+            rows.push(GridRow {
+                name: name.clone(),
+                cells,
+            });
+        }
+    }
+
+    Ok(Grid { rows, token })
+}
@@ -0,0 +1,115 @@
+//! Support for playing the audio and video files linked to a document in the
+//! time range of a match.
+
+use std::collections::HashMap;
+
+use graphannis::{
+    graph::{AnnoKey, NodeID},
+    model::AnnotationComponentType,
+    AnnotationGraph,
+};
+use graphannis_core::graph::ANNIS_NS;
+use serde::Serialize;
+
+use super::graph_storages;
+use crate::Result;
+
+/// A time range in seconds, as given by the `annis::time` annotation. Both the
+/// start and the end are optional.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct TimeRange {
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+}
+
+impl TimeRange {
+    /// Parse a value of the `annis::time` annotation like `0.5-1.25`.
+    pub fn parse(value: &str) -> Option<TimeRange> {
+        let (start, end) = value.split_once('-').unwrap_or((value, ""));
+        let start = start.trim().parse::<f64>().ok();
+        let end = end.trim().parse::<f64>().ok();
+        if start.is_none() && end.is_none() {
+            None
+        } else {
+            Some(TimeRange { start, end })
+        }
+    }
+
+    /// Create the smallest time range that includes both ranges.
+    fn union(self, other: TimeRange) -> TimeRange {
+        let min = |a: Option<f64>, b: Option<f64>| match (a, b) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            _ => a.or(b),
+        };
+        let max = |a: Option<f64>, b: Option<f64>| match (a, b) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            _ => a.or(b),
+        };
+        TimeRange {
+            start: min(self.start, other.start),
+            end: max(self.end, other.end),
+        }
+    }
+}
+
+/// Get the time range of a node from its `annis::time` annotation.
+pub fn time_range(g: &AnnotationGraph, node: NodeID) -> Result<Option<TimeRange>> {
+    let time_key = AnnoKey {
+        ns: ANNIS_NS.into(),
+        name: "time".into(),
+    };
+    let value = g.get_node_annos().get_value_for_item(&node, &time_key)?;
+    Ok(value.and_then(|v| TimeRange::parse(&v)))
+}
+
+/// Get the time range from the first to the last token covered by the
+/// matched nodes.
+pub fn match_time_range(
+    g: &AnnotationGraph,
+    matched: &HashMap<NodeID, usize>,
+) -> Result<Option<TimeRange>> {
+    let coverage = graph_storages(g, AnnotationComponentType::Coverage);
+    let mut result: Option<TimeRange> = None;
+    for node in matched.keys() {
+        let mut covered = vec![*node];
+        for gs in coverage.iter() {
+            for t in gs.get_outgoing_edges(*node) {
+                covered.push(t?);
+            }
+        }
+        for t in covered {
+            if let Some(range) = time_range(g, t)? {
+                result = Some(result.map_or(range, |r| r.union(range)));
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// A linked audio or video file.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MediaFile {
+    pub name: String,
+    pub mime_type: String,
+    pub is_video: bool,
+}
+
+/// Select the audio and video files from a list of linked file names.
+pub fn media_files(file_names: Vec<String>) -> Vec<MediaFile> {
+    file_names
+        .into_iter()
+        .filter_map(|name| {
+            let mime_type = mime_guess::from_path(&name).first()?;
+            let is_video = mime_type.type_() == mime_guess::mime::VIDEO;
+            if is_video || mime_type.type_() == mime_guess::mime::AUDIO {
+                Some(MediaFile {
+                    name,
+                    mime_type: mime_type.to_string(),
+                    is_video,
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
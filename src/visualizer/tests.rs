@@ -0,0 +1,11 @@
+use super::stacked_levels;
+
+#[test]
+fn overlapping_intervals_are_stacked() {
+    // Two short intervals next to each other, one interval spanning both and
+    // one that only overlaps with the spanning one
+    let intervals = vec![(0, 1), (2, 3), (0, 3), (3, 5)];
+    assert_eq!(vec![1, 1, 2, 3], stacked_levels(&intervals));
+    assert_eq!(vec![1, 1], stacked_levels(&[(0, 1), (4, 5)]));
+    assert_eq!(Vec::<usize>::new(), stacked_levels(&[]));
+}
@@ -0,0 +1,276 @@
+//! The tree visualizer lays out the dominance edges of a syntax tree, e.g.
+//! the `tiger` layer of a treebank, with the token as leaves at the bottom.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::Arc,
+};
+
+use graphannis::{
+    graph::{AnnoKey, GraphStorage, NodeID},
+    model::AnnotationComponentType,
+    AnnotationGraph,
+};
+use graphannis_core::{graph::ANNIS_NS, types::Edge};
+use serde::Serialize;
+
+use super::gap_edges;
+use crate::{converter::ordered_tokens, Result};
+
+/// Horizontal space reserved for each character of a label.
+const CHAR_WIDTH: f64 = 8.0;
+/// Minimal horizontal space reserved for each token.
+const MIN_TOKEN_WIDTH: f64 = 40.0;
+const LEVEL_HEIGHT: f64 = 60.0;
+const MARGIN: f64 = 20.0;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TreeNode {
+    pub x: f64,
+    pub y: f64,
+    pub label: String,
+    pub terminal: bool,
+    /// Position of the node in the match, if it is matched.
+    pub matched: Option<usize>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TreeEdge {
+    pub x1: f64,
+    pub y1: f64,
+    pub x2: f64,
+    pub y2: f64,
+    pub label: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Tree {
+    /// Layer of the dominance components this tree is created from.
+    pub layer: String,
+    pub width: f64,
+    pub height: f64,
+    pub nodes: Vec<TreeNode>,
+    pub edges: Vec<TreeEdge>,
+}
+
+/// Defines which components and annotations are used to create a tree.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TreeConfig {
+    /// Layer of the dominance components.
+    pub layer: String,
+    /// Name of the annotation used as label for the non-terminal nodes.
+    pub node_key: String,
+    /// Name of the edge annotation used as label for the edges.
+    pub edge_key: String,
+}
+
+impl TreeConfig {
+    pub fn new(layer: &str) -> Self {
+        Self {
+            layer: layer.to_string(),
+            node_key: "cat".to_string(),
+            edge_key: "func".to_string(),
+        }
+    }
+}
+
+/// Returns the layers of all dominance components in the graph where at
+/// least one of the dominating nodes has an annotation with the given name.
+pub fn tree_layers(g: &AnnotationGraph, node_key: &str) -> Result<BTreeSet<String>> {
+    let mut result = BTreeSet::new();
+    for c in g.get_all_components(Some(AnnotationComponentType::Dominance), None) {
+        if result.contains(c.layer.as_str()) {
+            continue;
+        }
+        if let Some(gs) = g.get_graphstorage(&c) {
+            for n in gs.source_nodes() {
+                if node_label(g, n?, node_key, &c.layer)?.is_some() {
+                    result.insert(c.layer.to_string());
+                    break;
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Get the value of an annotation with the given name, preferring the one in
+/// the namespace of the layer.
+fn node_label(
+    g: &AnnotationGraph,
+    node: NodeID,
+    name: &str,
+    layer: &str,
+) -> Result<Option<String>> {
+    let mut result = None;
+    for anno in g.get_node_annos().get_annotations_for_item(&node)? {
+        if anno.key.name == name && anno.key.ns != ANNIS_NS {
+            if anno.key.ns == layer {
+                return Ok(Some(anno.val.to_string()));
+            }
+            result = Some(anno.val.to_string());
+        }
+    }
+    Ok(result)
+}
+
+/// Lay out the tree for the dominance components of the configured layer.
+/// Non-terminal nodes are placed above the middle of their leftmost and
+/// rightmost child, so discontinuous constituents are spread over all of
+/// their children. Returns `None` if the layer has no dominance edges.
+pub fn create(
+    g: &AnnotationGraph,
+    config: &TreeConfig,
+    matched: &HashMap<NodeID, usize>,
+) -> Result<Option<Tree>> {
+    let components: Vec<Arc<dyn GraphStorage>> = g
+        .get_all_components(Some(AnnotationComponentType::Dominance), None)
+        .into_iter()
+        .filter(|c| c.layer == config.layer)
+        .filter_map(|c| g.get_graphstorage(&c))
+        .collect();
+
+    // Collect the (deduplicated) children of each non-terminal
+    let mut children: BTreeMap<NodeID, BTreeSet<NodeID>> = BTreeMap::new();
+    for gs in components.iter() {
+        for source in gs.source_nodes() {
+            let source = source?;
+            for target in gs.get_outgoing_edges(source) {
+                children.entry(source).or_default().insert(target?);
+            }
+        }
+    }
+    if children.is_empty() {
+        return Ok(None);
+    }
+
+    // Place the token from left to right at the bottom of the tree
+    let token_value_key = AnnoKey {
+        ns: ANNIS_NS.into(),
+        name: "tok".into(),
+    };
+    let mut x_position: HashMap<NodeID, f64> = HashMap::new();
+    let mut terminals = Vec::new();
+    let mut width = MARGIN;
+    for t in ordered_tokens(g, None, &gap_edges(g)?)? {
+        let label = g
+            .get_node_annos()
+            .get_value_for_item(&t.node, &token_value_key)?
+            .unwrap_or_default()
+            .to_string();
+        let token_width = (label.chars().count() as f64 * CHAR_WIDTH + MARGIN).max(MIN_TOKEN_WIDTH);
+        x_position.insert(t.node, width + token_width / 2.0);
+        width += token_width;
+        terminals.push((t.node, label));
+    }
+    width += MARGIN;
+
+    // The level of a node is its maximal distance to a token
+    let mut level: HashMap<NodeID, usize> = HashMap::new();
+    for n in children.keys() {
+        compute_level(*n, &children, &mut level, &mut BTreeSet::new());
+    }
+    let max_level = level.values().copied().max().unwrap_or_default();
+    let y_position = |n: &NodeID| -> f64 {
+        let l = level.get(n).copied().unwrap_or_default();
+        MARGIN + (max_level - l) as f64 * LEVEL_HEIGHT
+    };
+
+    // Place the non-terminals bottom up, so the position of all children is known
+    let mut non_terminals: Vec<NodeID> = children.keys().copied().collect();
+    non_terminals.sort_by_key(|n| level.get(n).copied().unwrap_or_default());
+    for n in non_terminals.iter() {
+        let child_x: Vec<f64> = children[n]
+            .iter()
+            .filter_map(|c| x_position.get(c).copied())
+            .collect();
+        if !child_x.is_empty() {
+            let min = child_x.iter().copied().fold(f64::MAX, f64::min);
+            let max = child_x.iter().copied().fold(f64::MIN, f64::max);
+            x_position.insert(*n, (min + max) / 2.0);
+        }
+    }
+
+    let mut nodes = Vec::new();
+    for n in non_terminals.iter() {
+        if let Some(x) = x_position.get(n) {
+            nodes.push(TreeNode {
+                x: *x,
+                y: y_position(n),
+                label: node_label(g, *n, &config.node_key, &config.layer)?.unwrap_or_default(),
+                terminal: false,
+                matched: matched.get(n).copied(),
+            });
+        }
+    }
+    for (n, label) in terminals {
+        nodes.push(TreeNode {
+            x: x_position[&n],
+            y: y_position(&n),
+            label,
+            terminal: true,
+            matched: matched.get(&n).copied(),
+        });
+    }
+
+    let mut edges = Vec::new();
+    for (source, targets) in children.iter() {
+        for target in targets {
+            if let (Some(x1), Some(x2)) = (x_position.get(source), x_position.get(target)) {
+                let edge = Edge {
+                    source: *source,
+                    target: *target,
+                };
+                let mut label = None;
+                for gs in components.iter() {
+                    for anno in gs.get_anno_storage().get_annotations_for_item(&edge)? {
+                        if anno.key.name == config.edge_key {
+                            label = Some(anno.val.to_string());
+                        }
+                    }
+                }
+                edges.push(TreeEdge {
+                    x1: *x1,
+                    y1: y_position(source),
+                    x2: *x2,
+                    y2: y_position(target),
+                    label,
+                });
+            }
+        }
+    }
+
+    Ok(Some(Tree {
+        layer: config.layer.clone(),
+        width,
+        height: MARGIN * 2.0 + max_level as f64 * LEVEL_HEIGHT,
+        nodes,
+        edges,
+    }))
+}
+
+fn compute_level(
+    node: NodeID,
+    children: &BTreeMap<NodeID, BTreeSet<NodeID>>,
+    level: &mut HashMap<NodeID, usize>,
+    visited: &mut BTreeSet<NodeID>,
+) -> usize {
+    if let Some(l) = level.get(&node) {
+        return *l;
+    }
+    // Ignore cycles, which are not allowed in dominance components
+    if !visited.insert(node) {
+        return 0;
+    }
+    let l = if let Some(node_children) = children.get(&node) {
+        node_children
+            .iter()
+            .map(|c| compute_level(*c, children, level, visited) + 1)
+            .max()
+            .unwrap_or_default()
+    } else {
+        0
+    };
+    level.insert(node, l);
+    l
+}
@@ -0,0 +1,172 @@
+//! Word lists count the token, segmentation nodes or annotation values of the
+//! selected corpora, optionally as n-grams and restricted to documents with a
+//! given metadata value.
+
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+};
+
+use graphannis::corpusstorage::{FrequencyDefEntry, QueryLanguage};
+use graphannis_core::{graph::ANNIS_NS, util::split_qname};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr, NoneAsEmptyString};
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    client::search::{self, FrequencyQuery},
+    state::{GlobalAppState, SessionArg},
+    Result,
+};
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct WordListConfig {
+    /// Number of consecutive items that are counted together.
+    #[serde_as(as = "DisplayFromStr")]
+    pub n: usize,
+    /// Count the nodes of this segmentation instead of the token.
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub segmentation: Option<String>,
+    /// Qualified name of the annotation to count, e.g. a lemma. The token or
+    /// segmentation value is used if not given.
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub annotation: Option<String>,
+    /// Only count in documents with this metadata value.
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub meta_name: Option<String>,
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub meta_value: Option<String>,
+}
+
+impl Default for WordListConfig {
+    fn default() -> Self {
+        Self {
+            n: 1,
+            segmentation: None,
+            annotation: None,
+            meta_name: None,
+            meta_value: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WordListEntry {
+    /// The values of the n-gram, separated by a space.
+    pub value: String,
+    pub count: usize,
+}
+
+fn quoted(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl WordListConfig {
+    /// The qualified name of the annotation whose values are counted.
+    fn counted_annotation(&self) -> String {
+        if let Some(annotation) = &self.annotation {
+            annotation.clone()
+        } else if let Some(segmentation) = &self.segmentation {
+            segmentation.clone()
+        } else {
+            format!("{ANNIS_NS}::tok")
+        }
+    }
+
+    /// Create the AQL query that matches all n-grams, e.g.
+    /// `lemma . lemma & Genre="Sport" & #1 @* #3`.
+    pub fn query(&self) -> String {
+        let n = self.n.max(1);
+        let node = match &self.annotation {
+            Some(annotation) => annotation.clone(),
+            None => self
+                .segmentation
+                .clone()
+                .unwrap_or_else(|| "tok".to_string()),
+        };
+        let precedence = match &self.segmentation {
+            Some(segmentation) => format!(" .{segmentation} "),
+            None => " . ".to_string(),
+        };
+        let mut query = vec![node; n].join(&precedence);
+        if let Some(meta_name) = &self.meta_name {
+            let meta = match &self.meta_value {
+                Some(value) => format!("{meta_name}={}", quoted(value)),
+                None => meta_name.clone(),
+            };
+            query.push_str(&format!(" & {meta} & #1 @* #{}", n + 1));
+        }
+        query
+    }
+
+    fn frequency_definition(&self) -> Vec<FrequencyDefEntry> {
+        let annotation = self.counted_annotation();
+        let (ns, name) = split_qname(&annotation);
+        (1..=self.n.max(1))
+            .map(|node_ref| FrequencyDefEntry {
+                ns: ns.map(str::to_string),
+                name: name.to_string(),
+                node_ref: node_ref.to_string(),
+            })
+            .collect()
+    }
+}
+
+/// Count the n-grams in each of the given corpora and sum them up. The
+/// progress is reported after each corpus. The result is sorted by the
+/// count, most frequent first.
+pub async fn create(
+    session: &SessionArg,
+    corpora: &[String],
+    config: &WordListConfig,
+    state: &GlobalAppState,
+    progress: Option<Sender<f32>>,
+) -> Result<Vec<WordListEntry>> {
+    let query = config.query();
+    let definition = config.frequency_definition();
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for (idx, corpus) in corpora.iter().enumerate() {
+        let frequency_query = FrequencyQuery {
+            query: query.clone(),
+            corpora: vec![corpus.clone()],
+            query_language: QueryLanguage::AQL,
+            definition: definition.clone(),
+        };
+        for row in search::frequency(session, &frequency_query, state).await? {
+            *counts.entry(row.values.join(" ")).or_default() += row.count;
+        }
+        if let Some(sender) = &progress {
+            sender.send((idx + 1) as f32 / corpora.len() as f32).await?;
+        }
+    }
+
+    let mut result: Vec<WordListEntry> = counts
+        .into_iter()
+        .map(|(value, count)| WordListEntry { value, count })
+        .collect();
+    result.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    Ok(result)
+}
+
+pub fn write_csv<W: Write>(entries: &[WordListEntry], output: W) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(output);
+    for entry in entries {
+        writer.serialize(entry)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn read_csv<R: Read>(input: R) -> Result<Vec<WordListEntry>> {
+    let mut reader = csv::Reader::from_reader(input);
+    let mut result = Vec::new();
+    for entry in reader.deserialize() {
+        result.push(entry?);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests;
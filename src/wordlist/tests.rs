@@ -0,0 +1,51 @@
+use super::{read_csv, write_csv, WordListConfig, WordListEntry};
+
+#[test]
+fn word_list_queries() {
+    assert_eq!("tok", WordListConfig::default().query());
+
+    let config = WordListConfig {
+        n: 3,
+        segmentation: Some("norm".to_string()),
+        ..Default::default()
+    };
+    assert_eq!("norm .norm norm .norm norm", config.query());
+
+    let config = WordListConfig {
+        n: 2,
+        annotation: Some("tiger::lemma".to_string()),
+        meta_name: Some("Genre".to_string()),
+        meta_value: Some("Sport \"Fußball\"".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        r#"tiger::lemma . tiger::lemma & Genre="Sport \"Fußball\"" & #1 @* #3"#,
+        config.query()
+    );
+    let definition = config.frequency_definition();
+    assert_eq!(2, definition.len());
+    assert_eq!(Some("tiger"), definition[1].ns.as_deref());
+    assert_eq!("lemma", definition[1].name);
+    assert_eq!("2", definition[1].node_ref);
+}
+
+#[test]
+fn csv_roundtrip() {
+    let entries = vec![
+        WordListEntry {
+            value: "die , der".to_string(),
+            count: 10,
+        },
+        WordListEntry {
+            value: "Feigenblatt".to_string(),
+            count: 1,
+        },
+    ];
+    let mut output = Vec::new();
+    write_csv(&entries, &mut output).unwrap();
+    assert_eq!(
+        "value,count\n\"die , der\",10\nFeigenblatt,1\n",
+        String::from_utf8_lossy(&output)
+    );
+    assert_eq!(entries, read_csv(&output[..]).unwrap());
+}